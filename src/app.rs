@@ -1,41 +1,90 @@
 // #![warn(clippy::all, clippy::pedantic)]
-use crate::conf::Config;
+use crate::audit::{self, Finding};
+use crate::breach::{Breaches, Hash, sha1_hex};
+use crate::conf::{Config, Expiry, SortKey};
+use crate::date::{self, DAY};
+use crate::folders::{self, Node};
+use crate::history::{Change, History};
+use crate::legacy::{self, LEGACY_FILE};
+use crate::otpauth;
+use crate::qr;
+use crate::search;
+use crate::strength::{self, Strength};
+use crate::totp::Totp;
+use crate::trash::{self, Trashed};
 use crate::ui::TableColors;
-use anyhow::{Context, Result};
-use clipboard::{ClipboardContext, ClipboardProvider};
-use csv::Writer;
+use crate::vault::{KdfParams, Payload, VAULT_FILE, Vault};
+use anyhow::{Context, Result, bail};
 use ratatui::{
 	style::palette::tailwind,
-	widgets::{ScrollbarState, TableState},
+	widgets::{ListState, ScrollbarState, TableState},
 };
 use serde::{Deserialize, Serialize};
-use std::{
-	borrow::Borrow,
-	error::Error,
-	fmt::Display,
-	fs::{self, File},
-	path::Path,
-};
+use std::{borrow::Borrow, collections::HashMap, fmt::Display, fs, path::PathBuf, time::Instant};
 use tui_input::Input;
+use uuid::Uuid;
 
-pub const PASSWORD_FILE: &str = "psu.csv";
-pub const TEMP_FILE: &str = "psu.csv.temp";
 pub const ITEM_HEIGHT: usize = 3;
 
 #[derive(Debug, Default, PartialEq)]
 pub enum CurrentScreen {
 	#[default]
+	Unlock,
+	// Plaintext psu.csv found next to an unlocked vault
+	Migrate,
 	Main,
 	Popup,
 	Help,
+	// Weak and reused passwords
+	Audit,
+	// QR code of the selected entry's TOTP
+	Qr,
+	// Typing into the / filter of the table
+	Search,
+	// Moving through the folders and tags of the sidebar
+	Sidebar,
+	// Typing the folder to move the selected entry to
+	Move,
+	// Every attribute of the selected entry
+	Detail,
+	// Yes or no on the action in `App::confirm`
+	Confirm,
+	// Deleted entries, to restore or purge them
+	Trash,
+}
+
+// Action waiting for a yes on the Confirm screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Confirm {
+	// Move the selected entry to the trash
+	Delete,
+	// Remove the selected trash entry for good
+	Purge,
+	EmptyTrash,
 }
 
 #[derive(Debug, Default, PartialEq)]
+pub enum AuditView {
+	#[default]
+	Findings,
+	Reused,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum InputMode {
 	#[default]
 	Service,
 	Login,
 	Password,
+	Confirm,
+	Totp,
+	Url,
+	Folder,
+	Expires,
+	Tags,
+	Notes,
+	// Custom field by position, the one after the last starts a new field
+	Field(usize),
 }
 
 #[derive(Default)]
@@ -45,6 +94,28 @@ pub struct App {
 	pub input: UserInput,
 	// Vector with passwords
 	pub items: Vec<Password>,
+	// Deleted entries and the selected one on the Trash screen
+	pub trash: Vec<Trashed>,
+	pub trash_state: TableState,
+	pub confirm: Option<Confirm>,
+	// Undo and redo of changes to the items and the trash
+	pub history: History,
+	// Indices into items of the rows the table shows, table rows map through this
+	pub view: Vec<usize>,
+	// What drawing each row of `view` needs, worked out once with it instead of every frame
+	pub view_rows: Vec<ViewRow>,
+	// Fuzzy filter on service and login
+	pub filter: Input,
+	// Folder or tag picked in the sidebar, narrows the table like the filter
+	pub node: Node,
+	pub sidebar: Vec<folders::Entry>,
+	pub sidebar_state: ListState,
+	pub show_sidebar: bool,
+	// Folder typed on the Move screen
+	pub folder_input: Input,
+	// Selected line of the Detail screen and the secret line shown in clear
+	pub detail_state: TableState,
+	pub detail_revealed: Option<usize>,
 	// Current field
 	pub input_mode: InputMode,
 	// Table
@@ -57,6 +128,31 @@ pub struct App {
 	pub current_screen: CurrentScreen,
 	// Need for handle mode input password
 	pub is_modify: bool,
+	// Problem with the form shown in its title
+	pub form_error: Option<String>,
+	// Master password form
+	pub unlock: UnlockForm,
+	// Opened vault, None while locked
+	pub vault: Option<Vault>,
+	// Audit screen results and selection
+	pub findings: Vec<Finding>,
+	pub reuse_groups: Vec<Vec<usize>>,
+	pub audit_view: AuditView,
+	pub audit_state: TableState,
+	// Breach counts from the last breach check, by password hash
+	pub breached: HashMap<Hash, u64>,
+	// One-off message in the footer, cleared by the next key
+	pub status: Option<String>,
+	// Lines of the QR code on the Qr screen
+	pub qr: Vec<String>,
+	// When the last copied secret gets cleared from the clipboard
+	pub clip_deadline: Option<Instant>,
+	// Every password is shown instead of bullets
+	pub reveal_all: bool,
+	// Entry whose password is shown until another row is selected
+	pub revealed: Option<usize>,
+	// Password fields of the form are shown instead of bullets
+	pub show_password: bool,
 }
 
 impl App {
@@ -65,25 +161,355 @@ impl App {
 			Config::gen_config().unwrap();
 			Config::new().unwrap_or_default()
 		});
-		let items = App::read(config.path.join(PASSWORD_FILE)).unwrap_or_default();
+
+		let mut unlock = UnlockForm::default();
+		let legacy_path = config.path.join(LEGACY_FILE);
+		if legacy_path.exists() && !config.path.join(VAULT_FILE).exists() {
+			match legacy::read(&legacy_path) {
+				Ok(items) => unlock.migrate = Some(items.len()),
+				Err(err) => {
+					unlock.migrate = Some(0);
+					unlock.error = Some(err.to_string());
+				}
+			}
+		}
 
 		Self {
+			reveal_all: config.table.reveal_passwords,
+			show_sidebar: config.table.sidebar,
+			revealed: None,
+			show_password: false,
 			config,
 			input: UserInput::default(),
 			input_mode: InputMode::default(),
 			current_screen: CurrentScreen::default(),
 			state: TableState::default().with_selected(0),
 			colors: TableColors::new(&tailwind::GRAY),
-			scroll_state: ScrollbarState::new(items.len().saturating_sub(ITEM_HEIGHT)),
+			scroll_state: ScrollbarState::default(),
 			is_modify: false,
+			form_error: None,
+			unlock,
+			vault: None,
+			findings: Vec::new(),
+			reuse_groups: Vec::new(),
+			audit_view: AuditView::default(),
+			audit_state: TableState::default(),
+			breached: HashMap::new(),
+			status: None,
+			qr: Vec::new(),
+			clip_deadline: None,
+			items: Vec::new(),
+			view: Vec::new(),
+			view_rows: Vec::new(),
+			filter: Input::default(),
+			node: Node::default(),
+			sidebar: Vec::new(),
+			sidebar_state: ListState::default(),
+			folder_input: Input::default(),
+			detail_state: TableState::default(),
+			detail_revealed: None,
+			trash: Vec::new(),
+			trash_state: TableState::default(),
+			confirm: None,
+			history: History::default(),
+		}
+	}
+
+	pub fn vault_path(&self) -> PathBuf {
+		self.config.path.join(VAULT_FILE)
+	}
+
+	pub fn legacy_path(&self) -> PathBuf {
+		self.config.path.join(LEGACY_FILE)
+	}
+
+	// Handle Enter on the unlock screen: open the vault or set up a new master password
+	pub fn unlock(&mut self) {
+		let master = self.unlock.input.value().to_owned();
+		self.unlock.input.reset();
+		self.unlock.error = None;
+
+		if self.vault_path().exists() {
+			match self.open_vault(&master) {
+				// Never keep working with a plaintext copy lying around
+				Ok(()) if self.legacy_path().exists() => self.current_screen = CurrentScreen::Migrate,
+				Ok(()) => {}
+				Err(err) => self.unlock.error = Some(err.to_string()),
+			}
+			return;
+		}
+
+		match self.unlock.pending.take() {
+			None if master.is_empty() => self.unlock.error = Some("Master password can't be empty.".into()),
+			None => self.unlock.pending = Some(master),
+			Some(pending) if pending != master => self.unlock.error = Some("Passwords do not match.".into()),
+			Some(_) => {
+				if let Err(err) = self.create_vault(&master) {
+					self.unlock.error = Some(err.to_string());
+				}
+			}
+		}
+	}
+
+	pub fn open_vault(&mut self, master: &str) -> Result<()> {
+		let (vault, payload) = Vault::open(self.vault_path(), master)?;
+		self.open(vault, payload);
+
+		let retention = self.config.trash.retention_days;
+		let purged = trash::purge_expired(&mut self.trash, retention, date::now());
+		if !purged.is_empty() {
+			self.forget(&purged);
+			self.write()?;
+		}
+		Ok(())
+	}
+
+	// Create a new vault, moving the rows of a legacy psu.csv into it when there is one
+	pub fn create_vault(&mut self, master: &str) -> Result<()> {
+		let items = match self.unlock.migrate {
+			Some(_) => legacy::read(self.legacy_path())?,
+			None => Vec::new(),
+		};
+
+		let vault = Vault::create(self.vault_path(), master, KdfParams::default())?;
+		let payload = Payload {
 			items,
+			..Default::default()
+		};
+		vault.save(&payload)?;
+
+		if self.unlock.migrate.is_some() {
+			// The plaintext file is removed only once the new vault is known to decrypt to the same rows
+			match Vault::open(self.vault_path(), master) {
+				Ok((_, saved)) if saved.items == payload.items => legacy::secure_remove(self.legacy_path())?,
+				_ => {
+					fs::remove_file(self.vault_path())?;
+					bail!("Failed to verify the new vault, {LEGACY_FILE} was kept.");
+				}
+			}
+			self.unlock.migrate = None;
+		}
+
+		self.open(vault, payload);
+		Ok(())
+	}
+
+	// Import rows of a leftover psu.csv that the vault doesn't have yet, then remove the file
+	pub fn merge_legacy(&mut self) -> Result<()> {
+		let mut missing: Vec<Password> = Vec::new();
+		for password in legacy::read(self.legacy_path())? {
+			let same = |item: &Password| item.ref_array()[1..] == password.ref_array()[1..];
+			if !self.items.iter().chain(&missing).any(same) {
+				missing.push(password);
+			}
+		}
+		if !missing.is_empty() {
+			self.checkpoint(format!("merge {LEGACY_FILE}"));
+			self.items.extend(missing);
+		}
+
+		self.write()?;
+		legacy::secure_remove(self.legacy_path())?;
+		self.refresh_view();
+		self.current_screen = CurrentScreen::Main;
+		Ok(())
+	}
+
+	fn open(&mut self, vault: Vault, payload: Payload) {
+		self.items = payload.items;
+		self.trash = payload.trash;
+		self.history = payload.history;
+		self.refresh_view();
+		self.vault = Some(vault);
+		self.current_screen = CurrentScreen::Main;
+	}
+
+	// Rebuild the rows after the items, the filter or the sort changed, the selection stays
+	// in range. Only the view is sorted, items keep the order they are saved in.
+	pub fn refresh_view(&mut self) {
+		self.refresh_sidebar();
+		let (items, query) = (&self.items, self.filter.value());
+		let mut view: Vec<(usize, ViewRow)> = (0..items.len())
+			.filter(|&index| self.node.contains(&items[index]))
+			.filter_map(|index| {
+				let matched = search::entry_match(query, &items[index])?;
+				let strength = strength::estimate(items[index].password());
+				Some((index, ViewRow { strength, matched }))
+			})
+			.collect();
+
+		match self.config.table.sort {
+			SortKey::Added => {}
+			SortKey::Service => view.sort_by_cached_key(|(index, _)| items[*index].service.to_lowercase()),
+			SortKey::Login => view.sort_by_cached_key(|(index, _)| items[*index].login.to_lowercase()),
+			SortKey::Modified => view.sort_by_key(|(index, _)| items[*index].modified),
+			SortKey::Strength => view.sort_by(|a, b| a.1.strength.bits.total_cmp(&b.1.strength.bits)),
+		}
+		if self.config.table.sort_descending {
+			view.reverse();
+		}
+
+		(self.view, self.view_rows) = view.into_iter().unzip();
+		self.scroll_state = ScrollbarState::new(self.view.len().saturating_sub(ITEM_HEIGHT));
+
+		let row = self.state.selected().unwrap_or(0);
+		self.select_row(row.min(self.view.len().saturating_sub(1)));
+	}
+
+	// The filter was edited, start over at the first match
+	pub fn apply_filter(&mut self) {
+		self.refresh_view();
+		self.select_row(0);
+	}
+
+	// Show every entry again, keeping the selected one selected
+	pub fn clear_filter(&mut self) {
+		self.filter.reset();
+		self.keep_selection(Self::refresh_view);
+	}
+
+	pub fn next_sort(&mut self) {
+		self.config.table.sort = self.config.table.sort.next();
+		self.keep_selection(Self::refresh_view);
+		self.save_sort();
+	}
+
+	pub fn reverse_sort(&mut self) {
+		self.config.table.sort_descending = !self.config.table.sort_descending;
+		self.keep_selection(Self::refresh_view);
+		self.save_sort();
+	}
+
+	fn save_sort(&mut self) {
+		if let Err(err) = self.config.save_sort() {
+			self.status = Some(format!("Failed to save the sort order: {err:#}"));
+		}
+	}
+
+	// Run `change` and select the entry that was selected before it
+	fn keep_selection(&mut self, change: impl FnOnce(&mut Self)) {
+		let selected = self.selected_index();
+		change(self);
+		if let Some(index) = selected {
+			self.select_item(index);
+		}
+	}
+
+	// Entry behind the selected row
+	pub fn selected_index(&self) -> Option<usize> {
+		self.state.selected().and_then(|row| self.view.get(row).copied())
+	}
+
+	fn select_row(&mut self, row: usize) {
+		if self.view.is_empty() {
+			self.state.select(None);
+			return;
+		}
+		self.state.select(Some(row));
+		self.scroll_state = self.scroll_state.position(row * ITEM_HEIGHT);
+	}
+
+	// Select an entry, dropping the filter and the sidebar node when they hide it
+	fn select_item(&mut self, index: usize) {
+		if !self.view.contains(&index) {
+			self.filter.reset();
+			self.node = Node::All;
+			self.refresh_view();
+		}
+		if let Some(row) = self.view.iter().position(|&i| i == index) {
+			self.select_row(row);
+		}
+	}
+
+	// Folders that emptied out disappear, the table falls back to every entry then
+	fn refresh_sidebar(&mut self) {
+		self.sidebar = folders::tree(&self.items);
+		let position = self.sidebar.iter().position(|entry| entry.node == self.node);
+		if position.is_none() {
+			self.node = Node::All;
+		}
+		self.sidebar_state.select(Some(position.unwrap_or(0)));
+	}
+
+	pub fn toggle_sidebar(&mut self) {
+		self.show_sidebar = !self.show_sidebar;
+	}
+
+	// Only "All" isn't worth showing, there is nothing to pick until an entry has a folder or tag
+	pub fn has_sidebar(&self) -> bool {
+		self.sidebar.len() > 1
+	}
+
+	pub fn focus_sidebar(&mut self) {
+		if !self.has_sidebar() {
+			self.status = Some("No folders or tags yet.".into());
+			return;
 		}
+		self.show_sidebar = true;
+		self.current_screen = CurrentScreen::Sidebar;
+	}
+
+	// Pick the next or previous sidebar line, the table follows right away
+	pub fn next_node(&mut self) {
+		let next = self
+			.sidebar_state
+			.selected()
+			.map_or(0, |i| (i + 1) % self.sidebar.len());
+		self.select_node(next);
+	}
+
+	pub fn previous_node(&mut self) {
+		let len = self.sidebar.len();
+		let previous = self.sidebar_state.selected().map_or(0, |i| (i + len - 1) % len);
+		self.select_node(previous);
+	}
+
+	pub fn select_node(&mut self, position: usize) {
+		if let Some(entry) = self.sidebar.get(position) {
+			self.node = entry.node.clone();
+			self.apply_filter();
+		}
+	}
+
+	// Start the Move screen with the selected entry's folder
+	pub fn open_move(&mut self) {
+		let Some(item) = self.selected_item() else {
+			return;
+		};
+		self.folder_input = item.folder.as_str().into();
+		self.current_screen = CurrentScreen::Move;
+	}
+
+	// Put the selected entry in the typed folder, an empty one takes it out of every folder.
+	// True if the entry moved.
+	pub fn move_selected(&mut self) -> bool {
+		let folder = folders::normalize(self.folder_input.value());
+		let mut moved = false;
+		if let Some(index) = self.selected_index() {
+			if self.items[index].folder != folder {
+				self.checkpoint(format!(
+					"move {} to {}",
+					self.items[index].name(),
+					Node::Folder(folder.clone()).name()
+				));
+				let item = &mut self.items[index];
+				item.folder = folder;
+				item.touch();
+				moved = true;
+			}
+			self.keep_selection(Self::refresh_view);
+		}
+		self.folder_input.reset();
+		moved
 	}
 
 	pub fn next_row(&mut self) {
+		if self.view.is_empty() {
+			return;
+		}
 		let i = match self.state.selected() {
 			Some(i) => {
-				if i >= self.items.len() - 1 {
+				if i >= self.view.len() - 1 {
 					0
 				} else {
 					i + 1
@@ -96,10 +522,13 @@ impl App {
 	}
 
 	pub fn previous_row(&mut self) {
+		if self.view.is_empty() {
+			return;
+		}
 		let i = match self.state.selected() {
 			Some(i) => {
 				if i == 0 {
-					self.items.len() - 1
+					self.view.len() - 1
 				} else {
 					i - 1
 				}
@@ -110,20 +539,264 @@ impl App {
 		self.scroll_state = self.scroll_state.position(i * ITEM_HEIGHT);
 	}
 
-	pub fn next_input_mode(&mut self) {
-		self.input_mode = match self.input_mode {
-			InputMode::Login => InputMode::Password,
-			InputMode::Password => InputMode::Service,
-			InputMode::Service => InputMode::Login,
+	pub fn toggle_reveal(&mut self) {
+		self.revealed = match self.revealed {
+			Some(index) if Some(index) == self.selected_index() => None,
+			_ => self.selected_index(),
 		};
 	}
 
-	pub fn prev_input_mode(&mut self) {
-		self.input_mode = match self.input_mode {
-			InputMode::Login => InputMode::Service,
-			InputMode::Password => InputMode::Login,
-			InputMode::Service => InputMode::Password,
+	pub fn toggle_reveal_all(&mut self) {
+		self.reveal_all = !self.reveal_all;
+		self.revealed = None;
+	}
+
+	pub fn is_revealed(&self, index: usize) -> bool {
+		self.reveal_all || (self.revealed == Some(index) && self.selected_index() == Some(index))
+	}
+
+	pub fn open_audit(&mut self) {
+		self.findings = audit::audit(
+			&self.items,
+			&self.breached,
+			&self.config.expiry,
+			&self.config.audit,
+			date::now(),
+		);
+		self.reuse_groups = audit::reuse_groups(&self.items);
+		self.audit_view = AuditView::default();
+		self.reset_audit_selection();
+		self.current_screen = CurrentScreen::Audit;
+	}
+
+	// Look every password up in the breach data from the config, the result goes to the footer
+	pub fn check_breaches(&mut self) {
+		self.status = Some(match self.find_breaches() {
+			Ok(0) => "No password found in breach data.".into(),
+			Ok(count) => format!("{count} of {} passwords found in breach data.", self.items.len()),
+			Err(err) => err.to_string(),
+		});
+	}
+
+	// The TOTP column only shows up once an entry has a secret
+	pub fn has_totp(&self) -> bool {
+		self.items.iter().any(|item| item.totp.is_some())
+	}
+
+	pub fn is_breached(&self, item: &Password) -> bool {
+		!self.breached.is_empty() && self.breached.contains_key(&sha1_hex(item.password()))
+	}
+
+	fn find_breaches(&mut self) -> Result<usize> {
+		let path = self
+			.config
+			.breaches
+			.as_ref()
+			.context("Set `breaches` in config.toml to a Have I Been Pwned hash file or range directory.")?;
+		self.breached = audit::breached(&self.items, &Breaches::new(path)?)?;
+
+		Ok(self.items.iter().filter(|item| self.is_breached(item)).count())
+	}
+
+	// QR code of the selected entry's otpauth:// URI, to scan it with a phone
+	pub fn open_qr(&mut self) {
+		let Some(item) = self.selected_item() else {
+			return;
+		};
+
+		match otpauth::to_uri(item).map(|uri| qr::render(&uri)) {
+			Some(Ok(lines)) => {
+				self.qr = lines;
+				self.current_screen = CurrentScreen::Qr;
+			}
+			Some(Err(err)) => self.status = Some(err.to_string()),
+			None => self.status = Some("Entry has no TOTP secret.".into()),
+		}
+	}
+
+	pub fn toggle_audit_view(&mut self) {
+		self.audit_view = match self.audit_view {
+			AuditView::Findings => AuditView::Reused,
+			AuditView::Reused => AuditView::Findings,
 		};
+		self.reset_audit_selection();
+	}
+
+	// Item indices in the order the current audit view lists them
+	pub fn audit_rows(&self) -> Vec<usize> {
+		match self.audit_view {
+			AuditView::Findings => self.findings.iter().map(|finding| finding.index).collect(),
+			AuditView::Reused => self.reuse_groups.concat(),
+		}
+	}
+
+	fn reset_audit_selection(&mut self) {
+		self.audit_state = TableState::default().with_selected((!self.audit_rows().is_empty()).then_some(0));
+	}
+
+	pub fn next_finding(&mut self) {
+		let len = self.audit_rows().len();
+		if len > 0 {
+			let i = self.audit_state.selected().map_or(0, |i| (i + 1) % len);
+			self.audit_state.select(Some(i));
+		}
+	}
+
+	pub fn previous_finding(&mut self) {
+		let len = self.audit_rows().len();
+		if len > 0 {
+			let i = self.audit_state.selected().map_or(0, |i| (i + len - 1) % len);
+			self.audit_state.select(Some(i));
+		}
+	}
+
+	// Leave the audit screen with the entry of the selected row selected in the table
+	pub fn jump_to_finding(&mut self) {
+		if let Some(index) = self
+			.audit_state
+			.selected()
+			.and_then(|i| self.audit_rows().get(i).copied())
+		{
+			self.select_item(index);
+		}
+		self.current_screen = CurrentScreen::Main;
+	}
+
+	pub fn open_detail(&mut self) {
+		if self.selected_item().is_some() {
+			self.detail_state = TableState::default().with_selected(0);
+			self.detail_revealed = None;
+			self.current_screen = CurrentScreen::Detail;
+		}
+	}
+
+	// Lines of the Detail screen for the selected entry
+	pub fn details(&self) -> Vec<Detail> {
+		self.selected_item()
+			.map(|item| item.details(self.config.expiry.max_age_days))
+			.unwrap_or_default()
+	}
+
+	pub fn next_detail(&mut self) {
+		let len = self.details().len();
+		if len > 0 {
+			let i = self.detail_state.selected().map_or(0, |i| (i + 1) % len);
+			self.detail_state.select(Some(i));
+		}
+	}
+
+	pub fn previous_detail(&mut self) {
+		let len = self.details().len();
+		if len > 0 {
+			let i = self.detail_state.selected().map_or(0, |i| (i + len - 1) % len);
+			self.detail_state.select(Some(i));
+		}
+	}
+
+	pub fn toggle_detail_reveal(&mut self) {
+		let selected = self.detail_state.selected();
+		self.detail_revealed = match self.detail_revealed {
+			Some(line) if Some(line) == selected => None,
+			_ => selected,
+		};
+	}
+
+	pub fn is_detail_revealed(&self, line: usize) -> bool {
+		self.reveal_all || self.detail_revealed == Some(line)
+	}
+
+	// Copy the value of the selected line, a TOTP line gives the code of the moment
+	pub fn clip_detail(&mut self) {
+		let details = self.details();
+		if let Some(detail) = self.detail_state.selected().and_then(|i| details.get(i)) {
+			self.copy(&detail.value);
+		}
+	}
+
+	// Make the old password on the selected line current again, true if it was restored.
+	// One that is the current password again leaves nothing to restore.
+	pub fn restore_password(&mut self) -> bool {
+		let details = self.details();
+		let (Some(old), Some(index)) = (
+			self.detail_state.selected().and_then(|i| details.get(i)?.old),
+			self.selected_index(),
+		) else {
+			return false;
+		};
+		if self.items[index].old_passwords[old].password == self.items[index].password {
+			return false;
+		}
+		self.checkpoint(format!("restore a password of {}", self.items[index].name()));
+		let keep = self.config.form.password_history;
+		if let Some(old) = self.items[index].restore_password(old, keep) {
+			self.items[index].touch();
+			self.status = Some(format!(
+				"Restored the password used until {}.",
+				date::format(old.replaced)
+			));
+			return true;
+		}
+		false
+	}
+
+	// Fields of the form in order, the confirmation only for new entries
+	pub fn form_fields(&self) -> Vec<InputMode> {
+		let mut fields = vec![InputMode::Service, InputMode::Login, InputMode::Password];
+		if self.needs_confirm() {
+			fields.push(InputMode::Confirm);
+		}
+		fields.extend([
+			InputMode::Totp,
+			InputMode::Url,
+			InputMode::Folder,
+			InputMode::Expires,
+			InputMode::Tags,
+			InputMode::Notes,
+		]);
+		fields.extend((0..=self.input.fields.len()).map(InputMode::Field));
+		fields
+	}
+
+	fn needs_confirm(&self) -> bool {
+		self.config.form.confirm_password && !self.is_modify
+	}
+
+	pub fn next_input_mode(&mut self) {
+		self.input.drop_blank_fields();
+		let fields = self.form_fields();
+		let index = fields.iter().position(|mode| *mode == self.input_mode);
+		self.input_mode = fields[index.map_or(0, |index| (index + 1) % fields.len())];
+	}
+
+	pub fn prev_input_mode(&mut self) {
+		self.input.drop_blank_fields();
+		let fields = self.form_fields();
+		let index = fields.iter().position(|mode| *mode == self.input_mode);
+		self.input_mode = fields[index.map_or(0, |index| (index + fields.len() - 1) % fields.len())];
+	}
+
+	// Fill the password field from the configured generator
+	pub fn generate_password(&mut self) {
+		match self.config.generator.generate() {
+			Ok(password) => {
+				self.input.confirm = password.as_str().into();
+				self.input.password = password.into();
+				self.form_error = None;
+			}
+			Err(err) => self.form_error = Some(err.to_string()),
+		}
+	}
+
+	// Fill the password field with a passphrase from the configured word list
+	pub fn generate_passphrase(&mut self) {
+		match self.config.passphrase.generate() {
+			Ok((passphrase, _)) => {
+				self.input.confirm = passphrase.as_str().into();
+				self.input.password = passphrase.into();
+				self.form_error = None;
+			}
+			Err(err) => self.form_error = Some(err.to_string()),
+		}
 	}
 
 	pub fn nex_column(&mut self) {
@@ -134,122 +807,333 @@ impl App {
 		self.state.select_previous_column();
 	}
 
-	pub fn add_password(&mut self) {
+	pub fn add_password(&mut self) -> Result<()> {
+		if self.needs_confirm() && self.input.password() != self.input.confirm.value() {
+			bail!("Passwords don't match.");
+		}
+		let totp = self.form_totp()?;
+		let fields = self.input.custom_fields()?;
+		let expires = match self.input.expires.value().trim() {
+			"" => None,
+			text => Some(date::parse(text)?),
+		};
 		let (service, login, password) = self.input.ref_array().into();
+		self.revealed = None;
 
-		if self.is_modify {
-			if let Some(index) = self.state.selected() {
-				let data = &mut self.items[index];
+		// The form works on a copy, so an edit that changes nothing leaves nothing to undo
+		let modified = match (self.is_modify, self.selected_index()) {
+			(true, Some(index)) => Some(index),
+			(true, None) => return Ok(()),
+			(false, _) => None,
+		};
+		if is_duplicate(&self.items, modified, service, login) {
+			bail!("Entry for {login} on {service} already exists.");
+		}
+		let mut data = match modified {
+			Some(index) => self.items[index].clone(),
+			None => Password::new(service, login, password),
+		};
+		data.login = login.into();
+		data.set_password(password, self.config.form.password_history);
+		data.service = service.into();
+		data.totp = totp;
+		data.expires = expires;
+		data.url = self.input.url.value().trim().into();
+		data.folder = folders::normalize(self.input.folder.value());
+		data.tags = parse_tags(self.input.tags.value());
+		data.notes = self.input.notes.value().into();
+		data.fields = fields;
+		if let Some(index) = modified
+			&& self.items[index] == data
+		{
+			return Ok(());
+		}
+		data.touch();
+
+		self.checkpoint(match modified {
+			Some(_) => format!("edit {}", data.name()),
+			None => format!("add {}", data.name()),
+		});
+		match modified {
+			Some(index) => self.items[index] = data,
+			None => self.items.push(data),
+		}
+		self.refresh_view();
+
+		Ok(())
+	}
+
+	// TOTP from the form field, a base32 secret or an otpauth:// URI. An edited entry keeps
+	// its algorithm, digits and period as long as the secret stays the same
+	fn form_totp(&self) -> Result<Option<Totp>> {
+		let secret = self.input.totp.value().trim();
+		if secret.is_empty() {
+			return Ok(None);
+		}
 
-				data.login = login.into();
-				data.password = password.into();
-				data.service = service.into();
+		let totp = if secret.starts_with("otpauth") {
+			let mut items = otpauth::parse(secret)?;
+			if items.len() != 1 {
+				bail!("Exports with several codes go through `psu import`.");
 			}
+			items.remove(0).totp.context("URI has no TOTP.")?
 		} else {
-			let new_id = self.items.len() as u32;
-			let data = Password::new(new_id, service, login, password);
-			self.items.push(data);
-		}
+			Totp::from_secret(secret)?
+		};
+		let current = self
+			.selected_index()
+			.filter(|_| self.is_modify)
+			.and_then(|index| self.items[index].totp.as_ref());
+
+		Ok(Some(match current {
+			Some(current) if current.secret() == totp.secret() => current.clone(),
+			_ => totp,
+		}))
 	}
 
-	// Write password to PASSWORD_FILE
+	// Encrypt passwords into VAULT_FILE
 	pub fn write(&mut self) -> Result<()> {
-		let final_path = self.config.path.join(PASSWORD_FILE);
-		let temp_path = self.config.path.join(TEMP_FILE);
+		let vault = self.vault.as_ref().context("Vault is locked.")?;
+		vault.save(&Payload {
+			items: self.items.clone(),
+			trash: self.trash.clone(),
+			history: match self.config.undo.persist {
+				true => self.history.clone(),
+				false => History::default(),
+			},
+		})
+	}
 
-		create_csv_file(&temp_path)?;
-		let mut wtr = get_writer(&temp_path)?;
+	// Remember the items and trash before a change, so it can be undone
+	pub fn checkpoint(&mut self, label: String) {
+		let change = Change {
+			label,
+			items: self.items.clone(),
+			trash: self.trash.clone(),
+		};
+		self.history.record(change, self.config.undo.limit);
+	}
 
-		for (index, password) in self.items.iter_mut().enumerate() {
-			if password.id != index as u32 {
-				password.id = index as u32;
+	// Purged entries can't come back, not even through undo
+	pub fn forget(&mut self, ids: &[Uuid]) {
+		self.history.forget(ids, &self.items, &self.trash);
+	}
+
+	// Roll the last change back, true if there was one
+	pub fn undo(&mut self) -> bool {
+		self.step(History::undo, "Undone", "Nothing to undo.")
+	}
+
+	pub fn redo(&mut self) -> bool {
+		self.step(History::redo, "Redone", "Nothing to redo.")
+	}
+
+	fn step(&mut self, direction: fn(&mut History, &mut Change) -> Option<String>, done: &str, nothing: &str) -> bool {
+		let mut current = Change {
+			label: String::new(),
+			items: std::mem::take(&mut self.items),
+			trash: std::mem::take(&mut self.trash),
+		};
+		let label = direction(&mut self.history, &mut current);
+		self.items = current.items;
+		self.trash = current.trash;
+
+		self.status = Some(match &label {
+			Some(label) => format!("{done}: {label}."),
+			None => nothing.into(),
+		});
+		self.revealed = None;
+		self.refresh_view();
+		self.select_trash(self.trash_state.selected().unwrap_or(0));
+		label.is_some()
+	}
+
+	// Ask before `action` happens, the Confirm screen runs it on a yes
+	pub fn ask(&mut self, action: Confirm) {
+		let nothing = match action {
+			Confirm::Delete => self.selected_index().is_none(),
+			Confirm::Purge => self.trash_state.selected().is_none(),
+			Confirm::EmptyTrash => self.trash.is_empty(),
+		};
+		if !nothing {
+			self.confirm = Some(action);
+			self.current_screen = CurrentScreen::Confirm;
+		}
+	}
+
+	// Run the confirmed action and go back to where it was asked
+	pub fn confirmed(&mut self) {
+		self.current_screen = match self.confirm.take() {
+			Some(Confirm::Delete) => {
+				self.delete();
+				CurrentScreen::Main
+			}
+			Some(Confirm::Purge) => {
+				self.purge();
+				CurrentScreen::Trash
 			}
+			Some(Confirm::EmptyTrash) => {
+				let purged: Vec<Uuid> = self.trash.drain(..).map(|trashed| trashed.item.id).collect();
+				self.forget(&purged);
+				self.select_trash(0);
+				CurrentScreen::Trash
+			}
+			None => CurrentScreen::Main,
+		};
+	}
 
-			wtr.write_record(password.ref_array())?;
+	pub fn cancel_confirm(&mut self) {
+		self.current_screen = match self.confirm.take() {
+			Some(Confirm::Purge | Confirm::EmptyTrash) => CurrentScreen::Trash,
+			_ => CurrentScreen::Main,
+		};
+	}
+
+	// Move the selected entry to the trash
+	pub fn delete(&mut self) {
+		self.revealed = None;
+		if let Some(index) = self.selected_index() {
+			self.checkpoint(format!("delete {}", self.items[index].name()));
+			self.trash.push(Trashed {
+				item: self.items.remove(index),
+				deleted: date::now(),
+			});
+			self.refresh_view();
 		}
-		wtr.flush()?;
+	}
 
-		fs::rename(temp_path, final_path)?;
-		Ok(())
+	// Newest first on the Trash screen
+	pub fn trash_rows(&self) -> Vec<usize> {
+		(0..self.trash.len()).rev().collect()
 	}
 
-	pub fn read<T: AsRef<Path>>(path: T) -> Option<Vec<Password>> {
-		if let Ok(mut wtr) = csv::Reader::from_path(path) {
-			let vec: Vec<Password> = wtr.deserialize::<Password>().flatten().collect();
-			return Some(vec);
+	pub fn open_trash(&mut self) {
+		self.select_trash(0);
+		self.current_screen = CurrentScreen::Trash;
+	}
+
+	fn select_trash(&mut self, row: usize) {
+		let len = self.trash.len();
+		self.trash_state.select((len > 0).then(|| row.min(len - 1)));
+	}
+
+	fn selected_trash(&self) -> Option<usize> {
+		self.trash_state
+			.selected()
+			.and_then(|row| self.trash_rows().get(row).copied())
+	}
+
+	pub fn next_trash(&mut self) {
+		let len = self.trash.len();
+		if len > 0 {
+			let i = self.trash_state.selected().map_or(0, |i| (i + 1) % len);
+			self.trash_state.select(Some(i));
 		}
+	}
 
-		None
+	pub fn previous_trash(&mut self) {
+		let len = self.trash.len();
+		if len > 0 {
+			let i = self.trash_state.selected().map_or(0, |i| (i + len - 1) % len);
+			self.trash_state.select(Some(i));
+		}
 	}
 
-	pub fn delete(&mut self) {
-		if let Some(index) = self.state.selected() {
-			self.items.remove(index);
+	// Put the selected trash entry back at the end of the vault and select it in the table,
+	// true if it came back
+	pub fn restore(&mut self) -> bool {
+		let Some(index) = self.selected_trash() else {
+			return false;
+		};
+		// Same rule as adding and editing: a service and login pair only once
+		let item = &self.trash[index].item;
+		if is_duplicate(&self.items, None, &item.service, &item.login) {
+			self.status = Some(format!("Entry for {} on {} already exists.", item.login, item.service));
+			return false;
+		}
+		let row = self.trash_state.selected().unwrap_or(0);
+		self.checkpoint(format!("restore {}", item.name()));
+		self.items.push(self.trash.remove(index).item);
+		self.refresh_view();
+		self.select_item(self.items.len() - 1);
+		self.select_trash(row);
+		true
+	}
+
+	fn purge(&mut self) {
+		if let Some(index) = self.selected_trash() {
+			let row = self.trash_state.selected().unwrap_or(0);
+			let purged = self.trash.remove(index);
+			self.forget(&[purged.item.id]);
+			self.select_trash(row);
 		}
 	}
 
 	#[inline]
 	pub fn modify(&mut self) {
-		if let Some(index) = self.state.selected() {
+		if let Some(index) = self.selected_index() {
 			let data = &self.items[index];
 			self.input = data.into();
 		}
 		self.is_modify = true;
 	}
 
-	pub fn clip_row(&self) -> anyhow::Result<(), Box<dyn Error>> {
-		if let Some(index) = self.state.selected() {
-			let mut ctx = ClipboardContext::new()?;
-			let password = self.items.get(index).context("No get Password.")?;
+	// Seconds until the clipboard is cleared, None once that happened or nothing was copied
+	pub fn clip_left(&self) -> Option<u64> {
+		let left = self.clip_deadline?.checked_duration_since(Instant::now())?;
+		Some(left.as_secs_f64().ceil() as u64)
+	}
 
-			ctx.set_contents(password.to_string())?;
+	// Copy to the clipboard, a failure ends up in the footer instead of ending the TUI
+	fn copy(&mut self, text: &str) {
+		match self.config.clipboard.copy(text) {
+			Ok(deadline) => self.clip_deadline = deadline,
+			Err(err) => self.status = Some(format!("{err:#}")),
 		}
-
-		Ok(())
 	}
 
-	pub fn clip_password(&self) -> anyhow::Result<(), Box<dyn Error>> {
-		if let Some(index) = self.state.selected() {
-			let mut ctx = ClipboardContext::new()?;
-			let password = self.items.get(index).context("No get Password.")?;
+	fn selected_item(&self) -> Option<&Password> {
+		self.selected_index().and_then(|index| self.items.get(index))
+	}
 
-			ctx.set_contents(password.password().into())?;
+	pub fn clip_row(&mut self) {
+		if let Some(row) = self.selected_item().map(Password::to_string) {
+			self.copy(&row);
 		}
-
-		Ok(())
 	}
 
-	pub fn clip_column(&self) -> anyhow::Result<(), Box<dyn Error>> {
-		if let Some(index) = self.state.selected() {
-			let mut ctx = ClipboardContext::new()?;
-			let password = self.items.get(index).context("No get Password.")?;
+	pub fn clip_password(&mut self) {
+		if let Some(password) = self.selected_item().map(|item| item.password.clone()) {
+			self.copy(&password);
+		}
+	}
 
-			let data = match self.state.selected_column() {
-				Some(1) => password.login(),
-				Some(0) => password.service(),
-				_ => password.password(),
-			};
+	// Copy the current one-time code of the selected entry
+	pub fn clip_totp(&mut self) {
+		let Some(item) = self.selected_item() else {
+			return;
+		};
 
-			ctx.set_contents(data.into())?;
+		match item.totp.as_ref().map(|totp| totp.now().0) {
+			Some(code) => self.copy(&code),
+			None => self.status = Some("Entry has no TOTP secret.".into()),
 		}
-
-		Ok(())
 	}
-}
 
-fn get_writer<T: AsRef<Path>>(path: T) -> Result<Writer<File>> {
-	Ok(csv::WriterBuilder::new()
-		.delimiter(b',')
-		.quote_style(csv::QuoteStyle::NonNumeric)
-		.from_writer(std::fs::OpenOptions::new().create(true).append(true).open(path)?))
-}
-
-fn create_csv_file<T: AsRef<Path>>(path: T) -> Result<()> {
-	let mut wtr = get_writer(path)?;
-	wtr.write_record(["Id", "Service", "Login", "Password"])?;
-	wtr.flush()?;
+	pub fn clip_column(&mut self) {
+		let Some(item) = self.selected_item() else {
+			return;
+		};
 
-	Ok(())
+		let data = match self.state.selected_column() {
+			Some(1) => item.login().into(),
+			Some(0) => item.service().into(),
+			Some(3) => item.totp.as_ref().map(|totp| totp.now().0).unwrap_or_default(),
+			Some(4) => item.tags.join(", "),
+			_ => item.password().into(),
+		};
+		self.copy(&data);
+	}
 }
 
 pub trait Data {
@@ -260,11 +1144,61 @@ pub trait Data {
 	fn service(&self) -> &str;
 }
 
+#[derive(Debug, Default)]
+pub struct UnlockForm {
+	pub input: Input,
+	// First entry of a new master password, waiting for confirmation
+	pub pending: Option<String>,
+	pub error: Option<String>,
+	// Number of rows in a legacy psu.csv that will be moved into the new vault
+	pub migrate: Option<usize>,
+}
+
+// Same service and login may only appear once, `skip` is the entry being edited
+pub fn is_duplicate(items: &[Password], skip: Option<usize>, service: &str, login: &str) -> bool {
+	items
+		.iter()
+		.enumerate()
+		.any(|(index, item)| Some(index) != skip && item.service == service && item.login == login)
+}
+
+// Tags from comma separated text, without blanks and repeats
+pub fn parse_tags(text: &str) -> Vec<String> {
+	let mut tags: Vec<String> = Vec::new();
+	for tag in text.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+		if !tags.iter().any(|known| known == tag) {
+			tags.push(tag.into());
+		}
+	}
+	tags
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct UserInput {
 	pub service: Input,
 	pub login: Input,
 	pub password: Input,
+	// Password again, for new entries
+	pub confirm: Input,
+	// Base32 TOTP secret, optional
+	pub totp: Input,
+	pub url: Input,
+	// "/" separated, optional
+	pub folder: Input,
+	// YYYY-MM-DD, optional
+	pub expires: Input,
+	// Comma separated
+	pub tags: Input,
+	// Line breaks are typed with Ctrl-n
+	pub notes: Input,
+	pub fields: Vec<FieldInput>,
+}
+
+// Custom field in the form, "name: value"
+#[derive(Clone, Debug, Default)]
+pub struct FieldInput {
+	pub input: Input,
+	pub secret: bool,
 }
 
 impl UserInput {
@@ -272,6 +1206,53 @@ impl UserInput {
 		self.login.reset();
 		self.password.reset();
 		self.service.reset();
+		self.confirm.reset();
+		self.totp.reset();
+		self.url.reset();
+		self.folder.reset();
+		self.expires.reset();
+		self.tags.reset();
+		self.notes.reset();
+		self.fields.clear();
+	}
+
+	// Custom field input, the slot after the last one turns into a new field
+	pub fn field_mut(&mut self, index: usize) -> &mut FieldInput {
+		if index >= self.fields.len() {
+			self.fields.resize_with(index + 1, FieldInput::default);
+		}
+		&mut self.fields[index]
+	}
+
+	// Keys pressed on the new field slot create a field even when nothing gets typed
+	fn drop_blank_fields(&mut self) {
+		while self
+			.fields
+			.last()
+			.is_some_and(|field| field.input.value().is_empty() && !field.secret)
+		{
+			self.fields.pop();
+		}
+	}
+
+	// Custom fields of the form, blank ones are left out
+	fn custom_fields(&self) -> Result<Vec<Field>> {
+		self.fields
+			.iter()
+			.filter(|field| !field.input.value().trim().is_empty())
+			.map(|field| {
+				let text = field.input.value();
+				let (name, value) = text
+					.split_once(':')
+					.filter(|(name, _)| !name.trim().is_empty())
+					.with_context(|| format!("Custom field \"{text}\" needs to be \"name: value\"."))?;
+				Ok(Field {
+					name: name.trim().into(),
+					value: value.trim().into(),
+					secret: field.secret,
+				})
+			})
+			.collect()
 	}
 
 	pub fn ref_array(&self) -> [&str; 3] {
@@ -285,6 +1266,14 @@ impl UserInput {
 			service: value[0].as_ref().into(),
 			login: value[1].as_ref().into(),
 			password: value[2].as_ref().into(),
+			confirm: Input::default(),
+			totp: Input::default(),
+			url: Input::default(),
+			folder: Input::default(),
+			expires: Input::default(),
+			tags: Input::default(),
+			notes: Input::default(),
+			fields: Vec::new(),
 		}
 	}
 }
@@ -303,22 +1292,240 @@ impl Data for UserInput {
 	}
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Password {
-	pub id: u32,
+	// Random and never changed, unlike the position in the vault
+	pub id: Uuid,
 	pub service: String,
 	pub login: String,
 	pub password: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub totp: Option<Totp>,
+	// Unix times, None for entries saved before they were tracked
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub created: Option<u64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub modified: Option<u64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub password_changed: Option<u64>,
+	// Date the password has to be changed by
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub expires: Option<u64>,
+	#[serde(default, skip_serializing_if = "String::is_empty")]
+	pub url: String,
+	// "/" separated path, empty outside of every folder
+	#[serde(default, skip_serializing_if = "String::is_empty")]
+	pub folder: String,
+	#[serde(default, skip_serializing_if = "String::is_empty")]
+	pub notes: String,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub tags: Vec<String>,
+	// Recovery codes, security answers and the like
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub fields: Vec<Field>,
+	// Passwords this one replaced, oldest first
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub old_passwords: Vec<OldPassword>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct OldPassword {
+	pub password: String,
+	// Unix time it was replaced at
+	pub replaced: u64,
+}
+
+// Row of the table next to its index in `App::view`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViewRow {
+	pub strength: Strength,
+	// Search filter matches in the service and the login
+	pub matched: [Vec<usize>; 2],
+}
+
+// Line of the Detail screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct Detail {
+	pub label: String,
+	pub value: String,
+	// Masked until revealed
+	pub secret: bool,
+	// Index into the old passwords, for restoring one
+	pub old: Option<usize>,
+}
+
+impl Detail {
+	fn new<T: Into<String>>(label: T, value: T, secret: bool) -> Self {
+		Self {
+			label: label.into(),
+			value: value.into(),
+			secret,
+			old: None,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExpiryState {
+	Expired,
+	// Within the warning days of the config
+	Soon,
+}
+
+// Named extra value of an entry, secret ones are masked like passwords
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Field {
+	pub name: String,
+	pub value: String,
+	#[serde(default)]
+	pub secret: bool,
 }
 
 impl Password {
-	pub fn new<T: AsRef<str>>(id: u32, service: T, login: T, password: T) -> Self {
+	pub fn new<T: AsRef<str>>(service: T, login: T, password: T) -> Self {
+		let now = date::now();
 		Self {
-			id,
+			id: Uuid::new_v4(),
 			service: service.as_ref().into(),
 			login: login.as_ref().into(),
 			password: password.as_ref().into(),
+			totp: None,
+			created: Some(now),
+			modified: Some(now),
+			password_changed: Some(now),
+			expires: None,
+			url: String::new(),
+			folder: String::new(),
+			notes: String::new(),
+			tags: Vec::new(),
+			fields: Vec::new(),
+			old_passwords: Vec::new(),
+		}
+	}
+
+	// "service / login", how messages name an entry
+	pub fn name(&self) -> String {
+		format!("{} / {}", self.service, self.login)
+	}
+
+	// Record a change made now
+	pub fn touch(&mut self) {
+		self.modified = Some(date::now());
+	}
+
+	// Only a different password counts as a change. The replaced one goes to the old
+	// passwords, of which the latest `keep` stay.
+	pub fn set_password(&mut self, password: &str, keep: usize) {
+		if self.password != password {
+			let now = date::now();
+			let old = std::mem::replace(&mut self.password, password.into());
+			if !old.is_empty() {
+				self.old_passwords.push(OldPassword {
+					password: old,
+					replaced: now,
+				});
+			}
+			let extra = self.old_passwords.len().saturating_sub(keep);
+			self.old_passwords.drain(..extra);
+			self.password_changed = Some(now);
+		}
+	}
+
+	// Make an old password current again, the current one takes its place in the history
+	pub fn restore_password(&mut self, index: usize, keep: usize) -> Option<OldPassword> {
+		if index >= self.old_passwords.len() {
+			return None;
+		}
+		let old = self.old_passwords.remove(index);
+		self.set_password(&old.password, keep);
+		Some(old)
+	}
+
+	// Own expiry date or `max_age_days` after the last password change, whichever is first
+	pub fn expires_at(&self, max_age_days: u64) -> Option<u64> {
+		let aged = self
+			.password_changed
+			.filter(|_| max_age_days > 0)
+			.map(|changed| changed + max_age_days * DAY);
+		match (self.expires, aged) {
+			(Some(expires), Some(aged)) => Some(expires.min(aged)),
+			(expires, aged) => expires.or(aged),
+		}
+	}
+
+	// Every attribute with a value, the TOTP one holds the current code. Expiry counts
+	// `max_age_days` like `expires_at`.
+	pub fn details(&self, max_age_days: u64) -> Vec<Detail> {
+		let mut details = vec![
+			Detail::new("Service", &self.service, false),
+			Detail::new("Login", &self.login, false),
+			Detail::new("Password", &self.password, true),
+		];
+		if let Some(totp) = &self.totp {
+			details.push(Detail::new("TOTP".into(), totp.now().0, false));
+		}
+		let texts = [
+			("URL", &self.url),
+			("Folder", &self.folder),
+			("Tags", &self.tags.join(", ")),
+			("Notes", &self.notes),
+		];
+		details.extend(
+			texts
+				.into_iter()
+				.filter(|(_, value)| !value.is_empty())
+				.map(|(label, value)| Detail::new(label, value, false)),
+		);
+		details.extend(
+			self.fields
+				.iter()
+				.map(|field| Detail::new(&field.name, &field.value, field.secret)),
+		);
+
+		let times = [
+			("Created", self.created),
+			("Modified", self.modified),
+			("Password changed", self.password_changed),
+			("Expires", self.expires_at(max_age_days)),
+		];
+		details.extend(
+			times
+				.into_iter()
+				.filter_map(|(label, time)| Some(Detail::new(label.into(), date::format(time?), false))),
+		);
+		details.push(Detail::new("Id".into(), self.id.to_string(), false));
+		// Newest first
+		details.extend(self.old_passwords.iter().enumerate().rev().map(|(i, old)| Detail {
+			old: Some(i),
+			..Detail::new(
+				format!("Until {}", date::format(old.replaced)),
+				old.password.clone(),
+				true,
+			)
+		}));
+		details
+	}
+
+	// A maximum age is set but the entry never recorded a password change, like the ones saved
+	// or migrated before psu tracked them. It may be older than any limit.
+	pub fn age_unknown(&self, max_age_days: u64) -> bool {
+		max_age_days > 0 && self.password_changed.is_none()
+	}
+
+	pub fn expiry(&self, config: &Expiry, now: u64) -> Option<ExpiryState> {
+		if self.age_unknown(config.max_age_days) {
+			return Some(ExpiryState::Expired);
+		}
+		let at = self.expires_at(config.max_age_days)?;
+		if at <= now {
+			Some(ExpiryState::Expired)
+		} else if at - now <= config.warn_days * DAY {
+			Some(ExpiryState::Soon)
+		} else {
+			None
 		}
 	}
 
@@ -362,6 +1569,21 @@ impl<T: Borrow<Password>> From<T> for UserInput {
 			service: value.service().into(),
 			login: value.login().into(),
 			password: value.password().into(),
+			confirm: Input::default(),
+			totp: value.totp.as_ref().map(Totp::secret).unwrap_or_default().into(),
+			url: value.url.as_str().into(),
+			folder: value.folder.as_str().into(),
+			expires: value.expires.map(date::format).unwrap_or_default().into(),
+			tags: value.tags.join(", ").into(),
+			notes: value.notes.as_str().into(),
+			fields: value
+				.fields
+				.iter()
+				.map(|field| FieldInput {
+					input: format!("{}: {}", field.name, field.value).into(),
+					secret: field.secret,
+				})
+				.collect(),
 		}
 	}
 }
@@ -370,7 +1592,10 @@ impl<T: Borrow<Password>> From<T> for UserInput {
 mod test_app {
 	use crate::app::Password;
 
-	use super::UserInput;
+	use super::{App, Confirm, CurrentScreen, ExpiryState, Field, UserInput};
+	use crate::conf::{Expiry, SortKey};
+	use crate::date::{self, DAY};
+	use crate::folders::Node;
 
 	#[test]
 	fn user_input_ref_array() {
@@ -380,8 +1605,11 @@ mod test_app {
 
 	#[test]
 	fn password_ref_array() {
-		let user_input = Password::new(0, "a", "b", "c");
-		assert_eq!(user_input.ref_array(), ["0", "a", "b", "c"])
+		let user_input = Password::new("a", "b", "c");
+		assert_eq!(
+			user_input.ref_array(),
+			[user_input.id(), "a".into(), "b".into(), "c".into()]
+		)
 	}
 
 	#[test]
@@ -390,4 +1618,375 @@ mod test_app {
 		user_input.reset_data();
 		assert_eq!(user_input.ref_array(), ["", "", ""])
 	}
+
+	#[test]
+	fn form_details() {
+		let mut app = App::default();
+		app.config.form.confirm_password = false;
+		app.input = UserInput::from_array(["a", "b", "c"]);
+		app.input.tags = "work, ,home, work".into();
+		app.input.field_mut(1).input = "pin: 12:34".into();
+		app.input.fields[1].secret = true;
+		app.add_password().unwrap();
+
+		let item = &app.items[0];
+		assert_eq!(item.tags, ["work", "home"]);
+		let pin = Field {
+			name: "pin".into(),
+			value: "12:34".into(),
+			secret: true,
+		};
+		assert_eq!(item.fields, [pin]);
+		assert_eq!(UserInput::from(item).fields[0].input.value(), "pin: 12:34");
+
+		app.input.field_mut(0).input = "no colon".into();
+		assert!(app.add_password().is_err());
+	}
+
+	#[test]
+	fn duplicate_entries() {
+		let mut app = App::default();
+		app.config.form.confirm_password = false;
+		for service in ["a", "b"] {
+			app.input = UserInput::from_array([service, "me", "pw"]);
+			app.add_password().unwrap();
+		}
+		app.refresh_view();
+
+		app.input = UserInput::from_array(["a", "me", "other"]);
+		let err = app.add_password().unwrap_err();
+		assert_eq!(err.to_string(), "Entry for me on a already exists.");
+		assert_eq!(app.items.len(), 2);
+
+		// An edit may keep its own service and login, not take another one's
+		app.modify();
+		app.input.password = "new".into();
+		app.add_password().unwrap();
+		app.modify();
+		app.input.service = "b".into();
+		assert!(app.add_password().is_err());
+	}
+
+	#[test]
+	fn expiry() {
+		let mut item = Password::new("a", "b", "c");
+		let changed = item.password_changed.unwrap();
+		item.set_password("c", 10);
+		assert_eq!(item.password_changed, Some(changed));
+
+		let expiry = Expiry::default();
+		item.expires = Some(changed + 30 * DAY);
+		assert_eq!(item.expiry(&expiry, changed), None);
+		assert_eq!(item.expiry(&expiry, changed + 20 * DAY), Some(ExpiryState::Soon));
+		assert_eq!(item.expiry(&expiry, changed + 30 * DAY), Some(ExpiryState::Expired));
+
+		// The maximum age wins when it comes first
+		let expiry = Expiry {
+			max_age_days: 10,
+			..expiry
+		};
+		assert_eq!(item.expires_at(expiry.max_age_days), Some(changed + 10 * DAY));
+		assert_eq!(item.expiry(&expiry, changed), Some(ExpiryState::Soon));
+
+		// Entries from before password changes were recorded only expire with a maximum age
+		let mut old = Password::new("d", "e", "f");
+		old.password_changed = None;
+		assert_eq!(old.expiry(&Expiry::default(), changed), None);
+		assert_eq!(old.expiry(&expiry, changed), Some(ExpiryState::Expired));
+
+		let mut app = App::default();
+		app.config.form.confirm_password = false;
+		app.input = UserInput::from_array(["a", "b", "c"]);
+		app.input.expires = "2030-12-31".into();
+		app.add_password().unwrap();
+		assert_eq!(app.items[0].expires, Some(date::parse("2030-12-31").unwrap()));
+		app.input.service = "d".into();
+		app.input.expires = "31.12.2030".into();
+		assert!(app.add_password().is_err());
+	}
+
+	#[test]
+	fn entry_details() {
+		let mut item = Password::new("mail", "me", "secret");
+		item.notes = "line one\nline two".into();
+		item.fields = vec![Field {
+			name: "pin".into(),
+			value: "1234".into(),
+			secret: true,
+		}];
+		item.modified = None;
+
+		let details = item.details(0);
+		let labels: Vec<&str> = details.iter().map(|detail| detail.label.as_str()).collect();
+		assert_eq!(
+			labels,
+			[
+				"Service",
+				"Login",
+				"Password",
+				"Notes",
+				"pin",
+				"Created",
+				"Password changed",
+				"Id"
+			]
+		);
+		let secret: Vec<bool> = details.iter().map(|detail| detail.secret).collect();
+		assert_eq!(secret, [false, false, true, false, true, false, false, false]);
+
+		let mut app = App {
+			items: vec![item],
+			..Default::default()
+		};
+		app.refresh_view();
+		app.open_detail();
+		app.next_detail();
+		app.next_detail();
+		app.toggle_detail_reveal();
+		assert!(app.is_detail_revealed(2));
+		assert!(!app.is_detail_revealed(4));
+	}
+
+	#[test]
+	fn old_passwords() {
+		let mut item = Password::new("mail", "me", "one");
+		for password in ["two", "three", "four"] {
+			item.set_password(password, 2);
+		}
+		let old: Vec<&str> = item.old_passwords.iter().map(|old| old.password.as_str()).collect();
+		assert_eq!(old, ["two", "three"]);
+
+		// Newest first at the end of the details
+		let details = item.details(0);
+		let lines: Vec<(&str, Option<usize>)> = details[details.len() - 2..]
+			.iter()
+			.map(|detail| (detail.value.as_str(), detail.old))
+			.collect();
+		assert_eq!(lines, [("three", Some(1)), ("two", Some(0))]);
+
+		let mut app = App {
+			items: vec![item],
+			..Default::default()
+		};
+		app.refresh_view();
+		app.open_detail();
+		app.previous_detail();
+		assert!(app.restore_password());
+		let item = &app.items[0];
+		assert_eq!(item.password, "two");
+		let old: Vec<&str> = item.old_passwords.iter().map(|old| old.password.as_str()).collect();
+		assert_eq!(old, ["three", "four"]);
+
+		// Undone like any other change
+		app.undo();
+		assert_eq!(app.items[0].password, "four");
+
+		// Only old password lines restore anything
+		app.detail_state.select(Some(0));
+		assert!(!app.restore_password());
+		assert_eq!(app.items[0].password, "four");
+
+		// Changed back to an old password, restoring that one is no change
+		let mut item = Password::new("mail", "me", "one");
+		item.set_password("two", 2);
+		item.set_password("one", 2);
+		let mut app = App {
+			items: vec![item],
+			..Default::default()
+		};
+		app.refresh_view();
+		app.open_detail();
+		app.previous_detail();
+		assert!(!app.restore_password());
+		assert_eq!(app.items[0].old_passwords.len(), 2);
+		assert_eq!(app.status, None);
+		assert!(!app.undo());
+	}
+
+	#[test]
+	fn sidebar_nodes() {
+		let mut app = App {
+			items: vec![
+				Password::new("github", "me", "a"),
+				Password::new("gitlab", "me", "b"),
+				Password::new("mail", "you", "c"),
+			],
+			..Default::default()
+		};
+		app.current_screen = CurrentScreen::Main;
+		app.refresh_view();
+		app.focus_sidebar();
+		assert_eq!(app.current_screen, CurrentScreen::Main);
+
+		app.items[0].folder = "work/code".into();
+		app.items[1].folder = "work".into();
+		app.refresh_view();
+
+		// All, work, code, No folder
+		assert_eq!(app.sidebar.len(), 4);
+		app.focus_sidebar();
+		assert_eq!(app.current_screen, CurrentScreen::Sidebar);
+		app.next_node();
+		assert_eq!(app.node, Node::Folder("work".into()));
+		assert_eq!(app.view, [0, 1]);
+		app.next_node();
+		assert_eq!(app.view, [0]);
+
+		// Moving the last entry out of a folder drops the folder and shows everything again
+		app.folder_input = " home / ".into();
+		assert!(app.move_selected());
+		assert_eq!(app.items[0].folder, "home");
+		assert_eq!(app.node, Node::All);
+		assert_eq!(app.view, [0, 1, 2]);
+		assert_eq!(app.selected_index(), Some(0));
+
+		// Same folder again is no change
+		app.folder_input = "home".into();
+		assert!(!app.move_selected());
+	}
+
+	#[test]
+	fn undo_redo() {
+		let mut app = App::default();
+		app.config.form.confirm_password = false;
+		app.input = UserInput::from_array(["a", "b", "c"]);
+		app.add_password().unwrap();
+		app.refresh_view();
+		app.delete();
+		assert!(app.items.is_empty());
+
+		assert!(app.undo());
+		assert_eq!(app.status.as_deref(), Some("Undone: delete a / b."));
+		assert_eq!(app.items.len(), 1);
+		assert!(app.trash.is_empty());
+		assert!(app.undo());
+		assert!(app.items.is_empty());
+		assert!(!app.undo());
+		assert_eq!(app.status.as_deref(), Some("Nothing to undo."));
+
+		assert!(app.redo());
+		assert_eq!(app.items[0].service, "a");
+		assert_eq!(app.view, [0]);
+
+		// Submitting the form unchanged is no change, the delete can still be redone
+		app.modify();
+		app.add_password().unwrap();
+		assert_eq!(app.history.undo.len(), 1);
+		assert_eq!(app.history.redo.len(), 1);
+	}
+
+	#[test]
+	fn trash_and_restore() {
+		let mut app = App {
+			items: vec![Password::new("a", "b", "c"), Password::new("d", "e", "f")],
+			..Default::default()
+		};
+		app.refresh_view();
+
+		// Nothing happens until the yes
+		app.ask(Confirm::Delete);
+		assert_eq!(app.current_screen, CurrentScreen::Confirm);
+		app.cancel_confirm();
+		assert_eq!(app.items.len(), 2);
+
+		app.ask(Confirm::Delete);
+		app.confirmed();
+		assert_eq!(app.current_screen, CurrentScreen::Main);
+		assert_eq!(app.items.len(), 1);
+		assert_eq!(app.trash[0].item.service, "a");
+
+		app.open_trash();
+		assert!(app.restore());
+		assert!(app.trash.is_empty());
+		assert_eq!(app.selected_item().map(|item| item.service.as_str()), Some("a"));
+
+		// A new entry took its place in the meantime
+		app.delete();
+		app.items.push(Password::new("a", "b", "g"));
+		app.open_trash();
+		assert!(!app.restore());
+		assert_eq!(app.trash.len(), 1);
+		assert_eq!(app.status.as_deref(), Some("Entry for b on a already exists."));
+		app.items.pop();
+		app.restore();
+		app.refresh_view();
+		app.select_row(0);
+
+		app.next_row();
+		let purged = app.selected_item().unwrap().id;
+		app.delete();
+		app.open_trash();
+		app.ask(Confirm::EmptyTrash);
+		app.confirmed();
+		assert_eq!(app.current_screen, CurrentScreen::Trash);
+		assert!(app.trash.is_empty());
+
+		// Gone for good, no undo brings it back
+		while app.undo() {
+			assert!(app.items.iter().all(|item| item.id != purged));
+			assert!(app.trash.iter().all(|trashed| trashed.item.id != purged));
+		}
+	}
+
+	#[test]
+	fn filtered_rows() {
+		let mut app = App {
+			items: vec![
+				Password::new("github", "me", "a"),
+				Password::new("gitlab", "me", "b"),
+				Password::new("mail", "you", "c"),
+			],
+			..Default::default()
+		};
+		app.refresh_view();
+		app.next_row();
+		assert_eq!(app.selected_index(), Some(1));
+
+		app.filter = "mail".into();
+		app.apply_filter();
+		assert_eq!(app.view, [2]);
+		assert_eq!(app.selected_index(), Some(2));
+
+		app.delete();
+		let services: Vec<&str> = app.items.iter().map(|item| item.service.as_str()).collect();
+		assert_eq!(services, ["github", "gitlab"]);
+		assert_eq!(app.selected_index(), None);
+
+		app.filter = "ghme".into();
+		app.apply_filter();
+		app.clear_filter();
+		assert_eq!(app.view, [0, 1]);
+		assert_eq!(app.selected_index(), Some(0));
+	}
+
+	#[test]
+	fn sorted_rows() {
+		let mut app = App {
+			items: vec![
+				Password::new("b", "x", "correct horse battery staple"),
+				Password::new("C", "y", "password"),
+				Password::new("a", "z", "Tr0ub4dor&3"),
+			],
+			..Default::default()
+		};
+		app.items[0].modified = Some(20);
+		app.items[2].modified = Some(10);
+		app.items[1].modified = None;
+
+		let mut order = |sort, descending| {
+			app.config.table.sort = sort;
+			app.config.table.sort_descending = descending;
+			app.refresh_view();
+			app.view.clone()
+		};
+		assert_eq!(order(SortKey::Service, false), [2, 0, 1]);
+		assert_eq!(order(SortKey::Login, true), [2, 1, 0]);
+		assert_eq!(order(SortKey::Modified, false), [1, 2, 0]);
+		assert_eq!(order(SortKey::Strength, false), [1, 2, 0]);
+		assert_eq!(order(SortKey::Added, true), [2, 1, 0]);
+
+		// The records themselves stay in place
+		let services: Vec<&str> = app.items.iter().map(|item| item.service.as_str()).collect();
+		assert_eq!(services, ["b", "C", "a"]);
+	}
 }
@@ -0,0 +1,311 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Vault health checks: weak, reused, breached, expired and old passwords, riskiest entries first
+use crate::app::{Data, ExpiryState, Password};
+use crate::breach::{Breaches, Hash, sha1_hex};
+use crate::conf::{Audit, Expiry};
+use crate::date::DAY;
+use crate::strength::{self, Score, Strength};
+use anyhow::Result;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+	Weak(Strength),
+	// Number of entries sharing this password, including this one
+	Reused(usize),
+	// Times the password was seen in breach data
+	Breached(u64),
+	// Days since the entry expired, None when the age of the password isn't known
+	Expired(Option<u64>),
+	// Days since the password was changed, None when that isn't known
+	Old(Option<u64>),
+}
+
+impl Issue {
+	fn risk(&self) -> u32 {
+		match self {
+			Issue::Weak(strength) => match strength.score {
+				Score::VeryWeak => 40,
+				Score::Weak => 30,
+				Score::Fair => 10,
+				_ => 0,
+			},
+			Issue::Reused(count) => (15 * (*count as u32 - 1)).min(45),
+			Issue::Breached(_) => 50,
+			Issue::Expired(_) => 20,
+			Issue::Old(Some(_)) => 15,
+			Issue::Old(None) => 5,
+		}
+	}
+
+	pub fn describe(&self) -> String {
+		match self {
+			Issue::Weak(strength) => match strength.warning() {
+				"" => strength.score.label().into(),
+				warning => format!("{}: {warning}", strength.score.label()),
+			},
+			Issue::Reused(count) => format!("used by {count} entries"),
+			Issue::Breached(count) => format!("seen {count} times in breaches"),
+			Issue::Expired(Some(0)) => "expired today".into(),
+			Issue::Expired(Some(days)) => format!("expired {days} days ago"),
+			Issue::Expired(None) => "expired, unknown age".into(),
+			Issue::Old(Some(days)) => format!("unchanged for {days} days"),
+			Issue::Old(None) => "unknown age".into(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+	// Index into the audited items
+	pub index: usize,
+	pub risk: u32,
+	pub issues: Vec<Issue>,
+}
+
+// `breached` comes from `breached()`, empty if breach data wasn't checked
+pub fn audit(
+	items: &[Password],
+	breached: &HashMap<Hash, u64>,
+	expiry: &Expiry,
+	config: &Audit,
+	now: u64,
+) -> Vec<Finding> {
+	let mut uses = vec![1; items.len()];
+	for group in reuse_groups(items) {
+		for index in &group {
+			uses[*index] = group.len();
+		}
+	}
+
+	let mut findings: Vec<Finding> = items
+		.iter()
+		.enumerate()
+		.filter_map(|(index, item)| {
+			let mut issues = Vec::new();
+			// TOTP-only entries from an import have no password to be weak
+			if !item.password().is_empty() {
+				let strength = strength::estimate(item.password());
+				if strength.score < Score::Strong {
+					issues.push(Issue::Weak(strength));
+				}
+			}
+			if uses[index] > 1 {
+				issues.push(Issue::Reused(uses[index]));
+			}
+			if let Some(count) = breached.get(&sha1_hex(item.password())) {
+				issues.push(Issue::Breached(*count));
+			}
+			if item.expiry(expiry, now) == Some(ExpiryState::Expired) {
+				let days = match item.age_unknown(expiry.max_age_days) {
+					true => None,
+					false => item.expires_at(expiry.max_age_days).map(|at| (now - at) / DAY),
+				};
+				issues.push(Issue::Expired(days));
+			} else if let Some(days) = age(item, config.old_days, now) {
+				issues.push(Issue::Old(days));
+			}
+
+			let risk = issues.iter().map(Issue::risk).sum();
+			(risk > 0).then_some(Finding { index, risk, issues })
+		})
+		.collect();
+
+	findings.sort_by(|a, b| b.risk.cmp(&a.risk).then(a.index.cmp(&b.index)));
+	findings
+}
+
+// Entries below Score::Strong, weakest first
+pub fn weak(items: &[Password]) -> Vec<(usize, Strength)> {
+	let mut weak: Vec<(usize, Strength)> = items
+		.iter()
+		.enumerate()
+		.filter(|(_, item)| !item.password().is_empty())
+		.map(|(index, item)| (index, strength::estimate(item.password())))
+		.filter(|(_, strength)| strength.score < Score::Strong)
+		.collect();
+
+	weak.sort_by(|a, b| a.1.score.cmp(&b.1.score).then(a.0.cmp(&b.0)));
+	weak
+}
+
+// Entries whose password is at least `old_days` old, unknown ages first and then the oldest
+pub fn old(items: &[Password], old_days: u64, now: u64) -> Vec<(usize, Option<u64>)> {
+	let mut old: Vec<(usize, Option<u64>)> = items
+		.iter()
+		.enumerate()
+		.filter_map(|(index, item)| age(item, old_days, now).map(|days| (index, days)))
+		.collect();
+
+	let days = |age: Option<u64>| age.unwrap_or(u64::MAX);
+	old.sort_by(|a, b| days(b.1).cmp(&days(a.1)).then(a.0.cmp(&b.0)));
+	old
+}
+
+// Some with the days since the password changed when that is `old_days` or more, Some(None)
+// when the entry never recorded a change. 0 turns the check off.
+fn age(item: &Password, old_days: u64, now: u64) -> Option<Option<u64>> {
+	if old_days == 0 || item.password().is_empty() {
+		return None;
+	}
+	match item.password_changed {
+		Some(changed) => {
+			let days = now.saturating_sub(changed) / DAY;
+			(days >= old_days).then_some(Some(days))
+		}
+		None => Some(None),
+	}
+}
+
+// Breach counts by password hash, only for passwords found in the breach data. Keyed by
+// hash rather than index so results stay valid while entries are added and removed.
+pub fn breached(items: &[Password], breaches: &Breaches) -> Result<HashMap<Hash, u64>> {
+	let mut breached = HashMap::new();
+	for item in items.iter().filter(|item| !item.password().is_empty()) {
+		let hash = sha1_hex(item.password());
+		if breached.contains_key(&hash) {
+			continue;
+		}
+		if let Some(count) = breaches.count(&hash)? {
+			breached.insert(hash, count);
+		}
+	}
+	Ok(breached)
+}
+
+// Entries whose password is in `breached`, most seen first
+pub fn breached_entries(items: &[Password], breached: &HashMap<Hash, u64>) -> Vec<(usize, u64)> {
+	let mut entries: Vec<(usize, u64)> = items
+		.iter()
+		.enumerate()
+		.filter_map(|(index, item)| breached.get(&sha1_hex(item.password())).map(|count| (index, *count)))
+		.collect();
+
+	entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+	entries
+}
+
+// Indices of entries sharing a password, largest groups first. Only indices leave this
+// function, so callers can report reuse without ever touching the secrets.
+pub fn reuse_groups(items: &[Password]) -> Vec<Vec<usize>> {
+	let mut groups: HashMap<&str, Vec<usize>> = HashMap::new();
+	for (index, item) in items.iter().enumerate().filter(|(_, item)| !item.password().is_empty()) {
+		groups.entry(item.password()).or_default().push(index);
+	}
+
+	let mut groups: Vec<Vec<usize>> = groups.into_values().filter(|group| group.len() > 1).collect();
+	groups.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+	groups
+}
+
+#[cfg(test)]
+mod test_audit {
+	use super::{Issue, audit, old, reuse_groups, weak};
+	use crate::app::Password;
+	use crate::breach::sha1_hex;
+	use crate::conf::{Audit, Expiry};
+	use crate::date::DAY;
+	use crate::otpauth;
+	use std::collections::HashMap;
+
+	#[test]
+	fn riskiest_first() {
+		let items = [
+			Password::new("a", "a", "x7#Kq9!mT2$vLp4@wZ8&"),
+			Password::new("b", "b", "Bulldozer1987"),
+			Password::new("c", "c", "password"),
+			Password::new("d", "d", "password"),
+		];
+
+		let findings = audit(&items, &HashMap::new(), &Expiry::default(), &Audit::default(), 0);
+		let order: Vec<usize> = findings.iter().map(|finding| finding.index).collect();
+		assert_eq!(order, [2, 3, 1]);
+		assert!(findings[0].issues.contains(&Issue::Reused(2)));
+
+		// Strong passwords are flagged too once they show up in a breach
+		let breached = HashMap::from([(sha1_hex("x7#Kq9!mT2$vLp4@wZ8&"), 3)]);
+		let findings = audit(&items, &breached, &Expiry::default(), &Audit::default(), 0);
+		let order: Vec<usize> = findings.iter().map(|finding| finding.index).collect();
+		assert_eq!(order, [2, 3, 0, 1]);
+		assert_eq!(findings[2].issues, [Issue::Breached(3)]);
+	}
+
+	#[test]
+	fn expired() {
+		let mut items = [
+			Password::new("a", "a", "x7#Kq9!mT2$vLp4@wZ8&"),
+			Password::new("b", "b", "k3$Vw9!pQz7#Lm2@xR5&"),
+		];
+		// Both changed at the same second, whenever the clock ticks between them
+		let now = items[0].password_changed.unwrap();
+		items[1].password_changed = Some(now);
+		items[0].expires = Some(now - 3 * DAY);
+
+		let findings = audit(&items, &HashMap::new(), &Expiry::default(), &Audit::default(), now);
+		assert_eq!(findings.len(), 1);
+		assert_eq!(findings[0].issues, [Issue::Expired(Some(3))]);
+
+		// Too old counts as expired once a maximum age is set
+		let expiry = Expiry {
+			max_age_days: 90,
+			..Default::default()
+		};
+		let findings = audit(&items, &HashMap::new(), &expiry, &Audit::default(), now + 91 * DAY);
+		assert_eq!(findings[1].issues, [Issue::Expired(Some(1))]);
+
+		// Without a recorded change the age can't be trusted
+		items[1].password_changed = None;
+		let findings = audit(&items, &HashMap::new(), &expiry, &Audit::default(), now);
+		assert_eq!(findings[0].issues, [Issue::Expired(Some(3))]);
+		assert_eq!(findings[1].issues, [Issue::Expired(None)]);
+	}
+
+	#[test]
+	fn old_passwords() {
+		let mut items = [
+			Password::new("a", "a", "x7#Kq9!mT2$vLp4@wZ8&"),
+			Password::new("b", "b", "k3$Vw9!pQz7#Lm2@xR5&"),
+			Password::new("c", "c", "Qz7#Lm2@xR5&k3$Vw9!p"),
+		];
+		let now = items[0].password_changed.unwrap() + 400 * DAY;
+		items[1].password_changed = Some(now - 10 * DAY);
+		items[2].password_changed = None;
+
+		let findings = audit(&items, &HashMap::new(), &Expiry::default(), &Audit::default(), now);
+		let issues: Vec<(usize, &[Issue])> = findings
+			.iter()
+			.map(|finding| (finding.index, finding.issues.as_slice()))
+			.collect();
+		assert_eq!(
+			issues,
+			[(0, &[Issue::Old(Some(400))][..]), (2, &[Issue::Old(None)][..])]
+		);
+		assert_eq!(old(&items, 365, now), [(2, None), (0, Some(400))]);
+		assert!(old(&items, 0, now).is_empty());
+	}
+
+	#[test]
+	fn totp_only() {
+		let imported = otpauth::parse("otpauth://totp/a:a?secret=JBSWY3DPEHPK3PXP").unwrap();
+		let items = [imported[0].clone(), Password::new("b", "b", "password")];
+
+		let findings = audit(&items, &HashMap::new(), &Expiry::default(), &Audit::default(), 0);
+		let flagged: Vec<usize> = findings.iter().map(|finding| finding.index).collect();
+		assert_eq!(flagged, [1]);
+		let weak: Vec<usize> = weak(&items).iter().map(|(index, _)| *index).collect();
+		assert_eq!(weak, [1]);
+	}
+
+	#[test]
+	fn groups() {
+		let items = [
+			Password::new("a", "a", "one"),
+			Password::new("b", "b", "two"),
+			Password::new("c", "c", "one"),
+			Password::new("d", "d", "two"),
+			Password::new("e", "e", "two"),
+			Password::new("f", "f", "three"),
+		];
+		assert_eq!(reuse_groups(&items), [vec![1, 3, 4], vec![0, 2]]);
+	}
+}
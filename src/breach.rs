@@ -0,0 +1,175 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Offline lookups in Have I Been Pwned SHA-1 password dumps. Either one file sorted by hash
+// with "HASH:COUNT" lines, or a directory of range files named after the first 5 hex
+// characters of the hash ("ABCDE" or "ABCDE.txt") with "SUFFIX:COUNT" lines.
+use anyhow::{Context, Result, bail};
+use sha1::{Digest, Sha1};
+use std::{
+	cmp::Ordering,
+	fs::File,
+	io::{BufRead, BufReader, Seek, SeekFrom},
+	path::{Path, PathBuf},
+};
+
+const HASH_LEN: usize = 40;
+const PREFIX_LEN: usize = 5;
+const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+pub type Hash = [u8; HASH_LEN];
+
+// Uppercase hex SHA-1, the form used by HIBP
+pub fn sha1_hex(password: &str) -> Hash {
+	let digest = Sha1::digest(password.as_bytes());
+	let mut hex = [0u8; HASH_LEN];
+	for (i, byte) in digest.iter().enumerate() {
+		hex[i * 2] = HEX[(byte >> 4) as usize];
+		hex[i * 2 + 1] = HEX[(byte & 0xf) as usize];
+	}
+	hex
+}
+
+pub struct Breaches {
+	path: PathBuf,
+}
+
+impl Breaches {
+	pub fn new<T: AsRef<Path>>(path: T) -> Result<Self> {
+		let path = path.as_ref();
+		if !path.exists() {
+			bail!("Breach data {} doesn't exist.", path.display());
+		}
+
+		Ok(Self { path: path.into() })
+	}
+
+	// How many times the password appears in the dump, None if it doesn't
+	pub fn count(&self, hash: &Hash) -> Result<Option<u64>> {
+		if !self.path.is_dir() {
+			return search(&self.path, hash);
+		}
+
+		let prefix = std::str::from_utf8(&hash[..PREFIX_LEN]).unwrap();
+		let file = [prefix.to_owned(), format!("{prefix}.txt")]
+			.into_iter()
+			.map(|name| self.path.join(name))
+			.find(|path| path.exists());
+
+		match file {
+			Some(file) => search(file, &hash[PREFIX_LEN..]),
+			None => Ok(None),
+		}
+	}
+}
+
+// Binary search over byte offsets of a file whose lines are sorted by the part before ':'.
+// Every step seeks to the middle of the remaining range and reads the first whole line after it.
+fn search<T: AsRef<Path>>(path: T, key: &[u8]) -> Result<Option<u64>> {
+	let path = path.as_ref();
+	let mut reader = BufReader::new(File::open(path).with_context(|| format!("Failed to open {}.", path.display()))?);
+	let (mut lo, mut hi) = (0, reader.get_ref().metadata()?.len());
+	let mut line = Vec::new();
+
+	while lo < hi {
+		let mid = lo + (hi - lo) / 2;
+		// Skip the rest of the line `mid` points into, unless it's exactly at a line start
+		let start = match mid {
+			0 => reader.seek(SeekFrom::Start(0))?,
+			_ => {
+				reader.seek(SeekFrom::Start(mid - 1))?;
+				line.clear();
+				mid - 1 + reader.read_until(b'\n', &mut line)? as u64
+			}
+		};
+		if start >= hi {
+			hi = mid;
+			continue;
+		}
+
+		line.clear();
+		let end = start + reader.read_until(b'\n', &mut line)? as u64;
+		let (hash, count) = split_line(&line);
+
+		match compare(hash, key) {
+			Ordering::Equal => return Ok(Some(parse_count(count))),
+			Ordering::Less => lo = end,
+			Ordering::Greater => hi = mid,
+		}
+	}
+
+	Ok(None)
+}
+
+fn split_line(line: &[u8]) -> (&[u8], &[u8]) {
+	let line = line.trim_ascii_end();
+	match line.iter().position(|b| *b == b':') {
+		Some(colon) => (&line[..colon], &line[colon + 1..]),
+		None => (line, &[]),
+	}
+}
+
+// Dumps are uppercase but lowercase hex shouldn't break the search
+fn compare(hash: &[u8], key: &[u8]) -> Ordering {
+	hash.iter()
+		.map(u8::to_ascii_uppercase)
+		.cmp(key.iter().map(u8::to_ascii_uppercase))
+}
+
+fn parse_count(count: &[u8]) -> u64 {
+	std::str::from_utf8(count)
+		.ok()
+		.and_then(|count| count.parse().ok())
+		.unwrap_or(1)
+}
+
+#[cfg(test)]
+mod test_breach {
+	use super::{Breaches, sha1_hex};
+	use crate::vault::test_vault::temp_dir;
+	use std::fs;
+
+	fn dump(passwords: &[&str]) -> Vec<String> {
+		let mut lines: Vec<String> = passwords
+			.iter()
+			.enumerate()
+			.map(|(count, password)| format!("{}:{}", String::from_utf8_lossy(&sha1_hex(password)), count + 1))
+			.collect();
+		lines.sort();
+		lines
+	}
+
+	#[test]
+	fn known_hash() {
+		assert_eq!(&sha1_hex("password"), b"5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8");
+	}
+
+	#[test]
+	fn sorted_file() {
+		let passwords: Vec<String> = (0..500).map(|i| format!("password{i}")).collect();
+		let passwords: Vec<&str> = passwords.iter().map(String::as_str).collect();
+		let path = temp_dir("sorted_file").join("pwned.txt");
+		fs::write(&path, dump(&passwords).join("\r\n")).unwrap();
+
+		let breaches = Breaches::new(&path).unwrap();
+		for (i, password) in passwords.iter().enumerate() {
+			assert_eq!(breaches.count(&sha1_hex(password)).unwrap(), Some(i as u64 + 1));
+		}
+		assert_eq!(breaches.count(&sha1_hex("not in there")).unwrap(), None);
+	}
+
+	#[test]
+	fn range_directory() {
+		let dir = temp_dir("range_directory");
+		let hash = sha1_hex("password");
+		let prefix = String::from_utf8_lossy(&hash[..5]).to_string();
+		let suffix = String::from_utf8_lossy(&hash[5..]).to_string();
+		fs::write(
+			dir.join(format!("{prefix}.txt")),
+			format!("0000000000000000000000000000000000A:1\n{suffix}:42\nFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF:3\n"),
+		)
+		.unwrap();
+
+		let breaches = Breaches::new(&dir).unwrap();
+		assert_eq!(breaches.count(&hash).unwrap(), Some(42));
+		assert_eq!(breaches.count(&sha1_hex("not in there")).unwrap(), None);
+	}
+}
@@ -0,0 +1,842 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Non-interactive commands working on the same vault as the TUI
+use crate::app::{App, Data, ExpiryState, Field, Password, is_duplicate, parse_tags};
+use crate::audit;
+use crate::breach::Breaches;
+use crate::clipboard::{self, Backend};
+use crate::date;
+use crate::folders::{self, Node};
+use crate::generator::{Generator, Passphrase};
+use crate::legacy::LEGACY_FILE;
+use crate::otpauth;
+use crate::output::{self, Format};
+use crate::qr;
+use crate::totp::{Algorithm, DEFAULT_DIGITS, DEFAULT_PERIOD, Totp};
+use crate::trash::Trashed;
+use crate::vault::VaultError;
+use anyhow::{Context, Result, bail};
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use std::{
+	env,
+	fmt::Display,
+	io::{self, IsTerminal},
+	path::PathBuf,
+};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+pub const MASTER_PASSWORD_ENV: &str = "PSU_MASTER_PASSWORD";
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+	#[command(subcommand)]
+	pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+	/// Create a new vault, migrating a plaintext psu.csv if there is one
+	Init,
+	/// Print a random password or passphrase, options override the settings from the config
+	Generate(GenerateArgs),
+	/// Add a new entry, pass "-" as PASSWORD to read it from stdin
+	Add {
+		service: String,
+		login: String,
+		password: String,
+		#[command(flatten)]
+		totp: TotpArgs,
+		#[command(flatten)]
+		details: DetailArgs,
+	},
+	/// Print one entry
+	Get {
+		/// Entry id, or enough of its beginning to be unique
+		id: String,
+		#[arg(short, long, value_enum, default_value_t)]
+		format: Format,
+		/// Print only the value of this custom field
+		#[arg(long, value_name = "NAME", conflicts_with = "format")]
+		field: Option<String>,
+	},
+	/// Print all entries
+	#[command(visible_alias = "ls")]
+	List {
+		#[arg(short, long, value_enum, default_value_t)]
+		format: Format,
+		/// Only entries that are past their expiry
+		#[arg(long, conflicts_with = "expiring")]
+		expired: bool,
+		/// Only entries that are expired or expire within the warning days, as highlighted in the table
+		#[arg(long)]
+		expiring: bool,
+		/// Only entries in this folder and the ones below it
+		#[arg(long, value_name = "PATH")]
+		folder: Option<String>,
+	},
+	/// Move an entry to a folder, an empty FOLDER takes it out of every folder
+	Mv {
+		/// Entry id, or enough of its beginning to be unique
+		id: String,
+		/// "/" separated path like work/acme
+		folder: String,
+	},
+	/// Move an entry to the trash, `psu trash` restores it
+	Rm {
+		/// Entry id, or enough of its beginning to be unique
+		id: String,
+	},
+	/// Roll the last change back, needs `persist` in the [undo] config to reach past this run
+	Undo,
+	/// Apply the last undone change again
+	Redo,
+	/// Deleted entries, purged for good after `retention_days` of the config
+	Trash {
+		#[command(subcommand)]
+		command: TrashCommand,
+	},
+	/// Print the current TOTP code of an entry, seconds it stays valid go to stderr
+	Totp {
+		/// Entry id, or enough of its beginning to be unique
+		id: String,
+	},
+	/// Show the TOTP of an entry as a QR code, to move it to a phone
+	Qr {
+		/// Entry id, or enough of its beginning to be unique
+		id: String,
+	},
+	/// Add TOTP entries from otpauth:// or otpauth-migration:// URIs or PNG images of their QR codes
+	Import {
+		#[arg(required = true, value_name = "URI_OR_PNG")]
+		sources: Vec<String>,
+	},
+	/// Helper started after a copy, clears the clipboard after AFTER seconds
+	#[command(name = clipboard::CLEAR_COMMAND, hide = true)]
+	ClearClipboard {
+		after: u64,
+		#[arg(value_enum)]
+		backend: Backend,
+	},
+	/// Check for reused, weak and breached passwords, exits with 7 when any are found
+	Audit {
+		/// Only report passwords shared by several entries
+		#[arg(long)]
+		reused: bool,
+		/// Only report weak passwords
+		#[arg(long)]
+		weak: bool,
+		/// Only report passwords unchanged for `old_days` of the config or of unknown age
+		#[arg(long)]
+		old: bool,
+		/// Also look passwords up in a local Have I Been Pwned SHA-1 file or range directory
+		#[arg(long, value_name = "PATH")]
+		breaches: Option<PathBuf>,
+		#[arg(short, long, value_enum, default_value_t)]
+		format: Format,
+	},
+	/// Change fields of an entry, pass "-" as PASSWORD to read it from stdin
+	#[command(group(
+		ArgGroup::new("fields")
+			.required(true)
+			.multiple(true)
+			.args(["service", "login", "password", "totp", "url", "folder", "expires", "notes", "tags", "field", "secret_field"])
+	))]
+	Edit {
+		/// Entry id, or enough of its beginning to be unique
+		id: String,
+		#[arg(short, long)]
+		service: Option<String>,
+		#[arg(short, long)]
+		login: Option<String>,
+		#[arg(short, long)]
+		password: Option<String>,
+		#[command(flatten)]
+		totp: TotpArgs,
+		#[command(flatten)]
+		details: DetailArgs,
+	},
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TrashCommand {
+	/// Print the deleted entries, newest first
+	#[command(visible_alias = "ls")]
+	List {
+		#[arg(short, long, value_enum, default_value_t)]
+		format: Format,
+	},
+	/// Put a deleted entry back into the vault
+	Restore {
+		/// Id of the deleted entry, or enough of its beginning to be unique
+		id: String,
+	},
+	/// Remove deleted entries for good
+	Purge {
+		/// Id of the deleted entry, or enough of its beginning to be unique
+		#[arg(required_unless_present = "all")]
+		id: Option<String>,
+		/// Every entry in the trash
+		#[arg(long, conflicts_with = "id")]
+		all: bool,
+	},
+}
+
+#[derive(Args, Debug)]
+pub struct TotpArgs {
+	/// Base32 TOTP secret, "-" reads it from stdin, an empty one removes it in `edit`
+	#[arg(long, value_name = "SECRET")]
+	totp: Option<String>,
+	/// TOTP hash algorithm
+	#[arg(long, value_enum, default_value_t, requires = "totp")]
+	algorithm: Algorithm,
+	/// Digits of a TOTP code
+	#[arg(long, default_value_t = DEFAULT_DIGITS, requires = "totp")]
+	digits: u32,
+	/// Seconds a TOTP code is valid for
+	#[arg(long, default_value_t = DEFAULT_PERIOD, requires = "totp")]
+	period: u64,
+}
+
+impl TotpArgs {
+	// None when no secret was given or it's empty
+	fn build(self) -> Result<Option<Totp>> {
+		match self.totp.map(read_secret).transpose()? {
+			Some(secret) if !secret.is_empty() => {
+				Ok(Some(Totp::new(&secret, self.algorithm, self.digits, self.period)?))
+			}
+			_ => Ok(None),
+		}
+	}
+}
+
+#[derive(Args, Debug)]
+pub struct DetailArgs {
+	/// Address of the login page
+	#[arg(long)]
+	url: Option<String>,
+	/// Free text, "-" reads everything from stdin
+	#[arg(long)]
+	notes: Option<String>,
+	/// "/" separated folder path like work/acme, an empty one takes the entry out of every folder
+	#[arg(long, value_name = "PATH")]
+	folder: Option<String>,
+	/// Date the password has to be changed by as YYYY-MM-DD, an empty one removes it
+	#[arg(long, value_name = "DATE")]
+	expires: Option<String>,
+	/// Comma separated tags, replaces the current ones in `edit`
+	#[arg(long, value_delimiter = ',')]
+	tags: Option<Vec<String>>,
+	/// Custom field, repeat for more, an empty VALUE removes it in `edit`
+	#[arg(long, value_name = "NAME=VALUE")]
+	field: Vec<String>,
+	/// Custom field that is masked like a password
+	#[arg(long, value_name = "NAME=VALUE")]
+	secret_field: Vec<String>,
+}
+
+impl DetailArgs {
+	// Set what was given, custom fields replace ones with the same name
+	fn apply(self, data: &mut Password) -> Result<()> {
+		if let Some(url) = self.url {
+			data.url = url;
+		}
+		if let Some(notes) = self.notes {
+			data.notes = match notes.as_str() {
+				"-" => io::read_to_string(io::stdin())?.trim_end().into(),
+				_ => notes,
+			};
+		}
+		if let Some(folder) = self.folder {
+			data.folder = folders::normalize(&folder);
+		}
+		if let Some(expires) = self.expires {
+			data.expires = match expires.trim() {
+				"" => None,
+				date => Some(date::parse(date)?),
+			};
+		}
+		if let Some(tags) = self.tags {
+			data.tags = parse_tags(&tags.join(","));
+		}
+
+		let fields = self.field.into_iter().map(|field| (field, false));
+		for (field, secret) in fields.chain(self.secret_field.into_iter().map(|field| (field, true))) {
+			let (name, value) = field
+				.split_once('=')
+				.filter(|(name, _)| !name.is_empty())
+				.with_context(|| format!("Custom field \"{field}\" needs to be NAME=VALUE."))?;
+			data.fields.retain(|field| field.name != name);
+			if !value.is_empty() {
+				data.fields.push(Field {
+					name: name.into(),
+					value: value.into(),
+					secret,
+				});
+			}
+		}
+		Ok(())
+	}
+}
+
+#[derive(Args, Debug)]
+pub struct GenerateArgs {
+	/// Number of characters
+	#[arg(short, long)]
+	length: Option<usize>,
+	/// Leave out lowercase letters
+	#[arg(long)]
+	no_lowercase: bool,
+	/// Leave out uppercase letters
+	#[arg(long)]
+	no_uppercase: bool,
+	/// Leave out digits
+	#[arg(long)]
+	no_digits: bool,
+	/// Leave out symbols
+	#[arg(long)]
+	no_symbols: bool,
+	/// Characters that are never used
+	#[arg(short, long)]
+	exclude: Option<String>,
+	/// Don't require a character from every enabled class
+	#[arg(long)]
+	no_require: bool,
+	/// Print a passphrase of random words instead
+	#[arg(short, long)]
+	passphrase: bool,
+	/// Number of words
+	#[arg(short, long, requires = "passphrase")]
+	words: Option<usize>,
+	/// String between words
+	#[arg(short, long, requires = "passphrase")]
+	separator: Option<String>,
+	/// Start every word with a capital letter
+	#[arg(short, long, requires = "passphrase")]
+	capitalize: bool,
+	/// Append a digit to one of the words
+	#[arg(short, long, requires = "passphrase")]
+	number: bool,
+	/// Word list file with one word per line
+	#[arg(long, requires = "passphrase")]
+	wordlist: Option<PathBuf>,
+}
+
+impl GenerateArgs {
+	// Print the secret on stdout and its entropy on stderr, so scripts only capture the secret
+	fn run(self) -> Result<()> {
+		let config = App::new().config;
+		let (secret, entropy) = if self.passphrase {
+			self.apply_passphrase(config.passphrase).generate()?
+		} else {
+			let generator = self.apply(config.generator);
+			(generator.generate()?, generator.entropy())
+		};
+
+		println!("{secret}");
+		eprintln!("Entropy: {entropy:.1} bits");
+		Ok(())
+	}
+
+	fn apply_passphrase(&self, mut passphrase: Passphrase) -> Passphrase {
+		passphrase.words = self.words.unwrap_or(passphrase.words);
+		passphrase.separator = self.separator.clone().unwrap_or(passphrase.separator);
+		passphrase.capitalize |= self.capitalize;
+		passphrase.number |= self.number;
+		passphrase.wordlist = self.wordlist.clone().or(passphrase.wordlist);
+		passphrase
+	}
+
+	fn apply(self, mut generator: Generator) -> Generator {
+		generator.length = self.length.unwrap_or(generator.length);
+		generator.lowercase &= !self.no_lowercase;
+		generator.uppercase &= !self.no_uppercase;
+		generator.digits &= !self.no_digits;
+		generator.symbols &= !self.no_symbols;
+		generator.exclude = self.exclude.unwrap_or(generator.exclude);
+		generator.require_every_class &= !self.no_require;
+		generator
+	}
+}
+
+// Process exit codes, 2 is what clap uses for usage errors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+	Success = 0,
+	Failure = 1,
+	NotFound = 3,
+	Duplicate = 4,
+	Locked = 5,
+	NoVault = 6,
+	Findings = 7,
+}
+
+#[derive(Debug)]
+pub enum CliError {
+	NotFound(String),
+	Duplicate { service: String, login: String },
+	Locked(String),
+	NoVault,
+	Exists,
+	Findings(usize),
+}
+
+impl Display for CliError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			CliError::NotFound(id) => write!(f, "No entry with id {id}."),
+			CliError::Duplicate { service, login } => write!(f, "Entry for {login} on {service} already exists."),
+			CliError::Locked(reason) => write!(f, "Vault is locked: {reason}"),
+			CliError::Exists => write!(f, "Vault already exists."),
+			CliError::Findings(count) => write!(f, "Audit found {count} issue(s)."),
+			CliError::NoVault => write!(
+				f,
+				"No vault found, run `psu init` or psu without arguments to create one."
+			),
+		}
+	}
+}
+
+impl std::error::Error for CliError {}
+
+impl From<&anyhow::Error> for Status {
+	fn from(err: &anyhow::Error) -> Self {
+		match err.downcast_ref::<CliError>() {
+			Some(CliError::NotFound(_)) => Status::NotFound,
+			Some(CliError::Duplicate { .. } | CliError::Exists) => Status::Duplicate,
+			Some(CliError::Locked(_)) => Status::Locked,
+			Some(CliError::NoVault) => Status::NoVault,
+			Some(CliError::Findings(_)) => Status::Findings,
+			None => Status::Failure,
+		}
+	}
+}
+
+// Run a command and return the process exit code
+pub fn run(command: Command) -> i32 {
+	match execute(command) {
+		Ok(()) => Status::Success as i32,
+		Err(err) => {
+			eprintln!("Error: {err:#}");
+			Status::from(&err) as i32
+		}
+	}
+}
+
+fn execute(command: Command) -> Result<()> {
+	match command {
+		Command::Init => init(&mut App::new())?,
+		Command::ClearClipboard { after, backend } => App::new().config.clipboard.clear_later(after, backend)?,
+		Command::Generate(args) => args.run()?,
+		Command::Add {
+			service,
+			login,
+			password,
+			totp,
+			details,
+		} => {
+			let mut app = unlock()?;
+			let password = read_secret(password)?;
+			let totp = totp.build()?;
+			check_duplicate(&app.items, None, &service, &login)?;
+
+			let mut data = Password::new(service, login, password);
+			data.totp = totp;
+			details.apply(&mut data)?;
+			data.touch();
+			app.checkpoint(format!("add {}", data.name()));
+			app.items.push(data);
+			app.write()?;
+		}
+		Command::Get { id, format, field } => {
+			let app = unlock()?;
+			let item = &app.items[position(&app, &id)?];
+			match field {
+				Some(name) => {
+					let field = item
+						.fields
+						.iter()
+						.find(|field| field.name == name)
+						.with_context(|| format!("Entry {id} has no field {name}."))?;
+					println!("{}", field.value);
+				}
+				None => println!("{}", output::single(item, format)?),
+			}
+		}
+		Command::List {
+			format,
+			expired,
+			expiring,
+			folder,
+		} => {
+			let app = unlock()?;
+			let now = date::now();
+			let node = folder.map_or(Node::All, |folder| Node::Folder(folders::normalize(&folder)));
+			let items: Vec<Password> = app
+				.items
+				.iter()
+				.filter(|item| node.contains(item))
+				.filter(|item| match item.expiry(&app.config.expiry, now) {
+					Some(ExpiryState::Expired) => true,
+					Some(ExpiryState::Soon) => !expired,
+					None => !expired && !expiring,
+				})
+				.cloned()
+				.collect();
+			let out = output::list(&items, format)?;
+			if !out.is_empty() {
+				println!("{out}");
+			}
+		}
+		Command::Rm { id } => {
+			let mut app = unlock()?;
+			let index = position(&app, &id)?;
+			app.checkpoint(format!("delete {}", app.items[index].name()));
+			let item = app.items.remove(index);
+			app.trash.push(Trashed {
+				item,
+				deleted: date::now(),
+			});
+			app.write()?;
+		}
+		Command::Trash { command } => trash(command)?,
+		Command::Undo | Command::Redo => {
+			let mut app = unlock()?;
+			let done = match command {
+				Command::Undo => app.undo(),
+				_ => app.redo(),
+			};
+			if !done {
+				bail!(app.status.take().unwrap_or_default());
+			}
+			app.write()?;
+			eprintln!("{}", app.status.take().unwrap_or_default());
+		}
+		Command::Mv { id, folder } => {
+			let mut app = unlock()?;
+			let index = position(&app, &id)?;
+			let folder = folders::normalize(&folder);
+			if app.items[index].folder != folder {
+				app.checkpoint(format!(
+					"move {} to {}",
+					app.items[index].name(),
+					Node::Folder(folder.clone()).name()
+				));
+				let data = &mut app.items[index];
+				data.folder = folder;
+				data.touch();
+				app.write()?;
+			}
+		}
+		Command::Totp { id } => {
+			let app = unlock()?;
+			let (code, left) = app.items[position(&app, &id)?]
+				.totp
+				.as_ref()
+				.map(Totp::now)
+				.with_context(|| format!("Entry {id} has no TOTP secret."))?;
+
+			println!("{code}");
+			eprintln!("Valid for {left}s");
+		}
+		Command::Qr { id } => {
+			let app = unlock()?;
+			let uri = otpauth::to_uri(&app.items[position(&app, &id)?])
+				.with_context(|| format!("Entry {id} has no TOTP secret."))?;
+
+			for line in qr::render(&uri)? {
+				// Dark on light whatever the terminal theme is, or phones can't scan it
+				match io::stdout().is_terminal() {
+					true => println!("\x1b[30;47m{line}\x1b[0m"),
+					false => println!("{line}"),
+				}
+			}
+		}
+		Command::Import { sources } => {
+			let mut app = unlock()?;
+			let mut entries = Vec::new();
+			for source in sources {
+				entries.extend(read_import(&source)?);
+			}
+			if import(&mut app, entries) > 0 {
+				app.write()?;
+			}
+		}
+		Command::Audit {
+			reused,
+			weak,
+			old,
+			breaches,
+			format,
+		} => {
+			// Fail on a bad path before asking for the master password
+			let breaches = breaches.map(Breaches::new).transpose()?;
+			let app = unlock()?;
+			// Without --reused, --weak or --old all of them run
+			let all = !reused && !weak && !old;
+			let groups = (all || reused).then(|| audit::reuse_groups(&app.items));
+			let weak = (all || weak).then(|| audit::weak(&app.items));
+			let old = (all || old).then(|| audit::old(&app.items, app.config.audit.old_days, date::now()));
+			let breached = match breaches {
+				Some(breaches) => Some(audit::breached_entries(
+					&app.items,
+					&audit::breached(&app.items, &breaches)?,
+				)),
+				None => None,
+			};
+
+			let report = output::audit(
+				&app.items,
+				groups.as_deref(),
+				weak.as_deref(),
+				old.as_deref(),
+				breached.as_deref(),
+				format,
+			)?;
+			if !report.is_empty() {
+				println!("{report}");
+			}
+
+			let count = groups.map_or(0, |groups| groups.len())
+				+ weak.map_or(0, |weak| weak.len())
+				+ old.map_or(0, |old| old.len())
+				+ breached.map_or(0, |breached| breached.len());
+			if count > 0 {
+				bail!(CliError::Findings(count));
+			}
+		}
+		Command::Edit {
+			id,
+			service,
+			login,
+			password,
+			totp,
+			details,
+		} => {
+			let mut app = unlock()?;
+			let index = position(&app, &id)?;
+			let service = service.unwrap_or_else(|| app.items[index].service().into());
+			let login = login.unwrap_or_else(|| app.items[index].login().into());
+			check_duplicate(&app.items, Some(index), &service, &login)?;
+
+			// Same values as before leave nothing to undo
+			let mut data = app.items[index].clone();
+			if let Some(password) = password {
+				data.set_password(&read_secret(password)?, app.config.form.password_history);
+			}
+			if totp.totp.is_some() {
+				data.totp = totp.build()?;
+			}
+			details.apply(&mut data)?;
+			data.service = service;
+			data.login = login;
+			if data != app.items[index] {
+				data.touch();
+				app.checkpoint(format!("edit {}", app.items[index].name()));
+				app.items[index] = data;
+				app.write()?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn init(app: &mut App) -> Result<()> {
+	if app.vault_path().exists() {
+		bail!(CliError::Exists);
+	}
+
+	let master = master_password("New master password: ")?;
+	if env::var(MASTER_PASSWORD_ENV).is_err() && *master_password("Repeat master password: ")? != *master {
+		bail!("Passwords do not match.");
+	}
+	if master.is_empty() {
+		bail!("Master password can't be empty.");
+	}
+
+	app.create_vault(&master)
+}
+
+fn unlock() -> Result<App> {
+	let mut app = App::new();
+	if !app.vault_path().exists() {
+		bail!(CliError::NoVault);
+	}
+	if app.legacy_path().exists() {
+		bail!("Plaintext {LEGACY_FILE} found next to the vault, run psu without arguments to merge it.");
+	}
+
+	let master = master_password("Master password: ")?;
+	app.open_vault(&master)
+		.map_err(|err| match err.downcast_ref::<VaultError>() {
+			Some(VaultError::Decrypt) => CliError::Locked("wrong master password.".into()).into(),
+			_ => err,
+		})?;
+	Ok(app)
+}
+
+// Taken from PSU_MASTER_PASSWORD, otherwise asked on the terminal
+fn master_password(prompt: &str) -> Result<Zeroizing<String>> {
+	if let Ok(master) = env::var(MASTER_PASSWORD_ENV) {
+		return Ok(Zeroizing::new(master));
+	}
+
+	let master = rpassword::prompt_password(prompt)
+		.map_err(|_| CliError::Locked(format!("no terminal to ask for it, set {MASTER_PASSWORD_ENV}.")))?;
+	Ok(Zeroizing::new(master))
+}
+
+// Every source is read before anything changes, so a bad one imports nothing
+fn read_import(source: &str) -> Result<Vec<Password>> {
+	let uris = match source.contains("://") {
+		true => vec![source.to_owned()],
+		false => qr::decode_png(source)?,
+	};
+
+	let mut entries = Vec::new();
+	for uri in uris {
+		entries.extend(otpauth::parse(&uri)?);
+	}
+	Ok(entries)
+}
+
+// Entries with the same service and login get the TOTP added, others are new.
+// Returns how many changed, the undo step is only recorded for the first one.
+fn import(app: &mut App, entries: Vec<Password>) -> usize {
+	let mut changed = 0;
+	for mut entry in entries {
+		let existing = app
+			.items
+			.iter()
+			.position(|item| item.service() == entry.service() && item.login() == entry.login());
+		if existing.is_some_and(|index| app.items[index].totp == entry.totp) {
+			eprintln!("Unchanged {} {}", entry.service(), entry.login());
+			continue;
+		}
+
+		if changed == 0 {
+			app.checkpoint("import".into());
+		}
+		changed += 1;
+		match existing {
+			Some(index) => {
+				eprintln!("Updated TOTP of {} {}", entry.service(), entry.login());
+				app.items[index].totp = entry.totp;
+				app.items[index].touch();
+			}
+			None => {
+				eprintln!("Added {} {}", entry.service(), entry.login());
+				entry.touch();
+				app.items.push(entry);
+			}
+		}
+	}
+	changed
+}
+
+fn trash(command: TrashCommand) -> Result<()> {
+	let mut app = unlock()?;
+	match command {
+		TrashCommand::List { format } => {
+			let out = output::trash(&app.trash, format)?;
+			if !out.is_empty() {
+				println!("{out}");
+			}
+		}
+		TrashCommand::Restore { id } => {
+			let index = find(app.trash.iter().map(|trashed| &trashed.item), &id)?;
+			let item = &app.trash[index].item;
+			check_duplicate(&app.items, None, item.service(), item.login())?;
+			app.checkpoint(format!("restore {}", app.trash[index].item.name()));
+			let item = app.trash.remove(index).item;
+			app.items.push(item);
+			app.write()?;
+		}
+		TrashCommand::Purge { id: Some(id), .. } => {
+			let index = find(app.trash.iter().map(|trashed| &trashed.item), &id)?;
+			let purged = app.trash.remove(index);
+			app.forget(&[purged.item.id]);
+			app.write()?;
+		}
+		TrashCommand::Purge { id: None, .. } => {
+			if !app.trash.is_empty() {
+				let purged: Vec<Uuid> = app.trash.drain(..).map(|trashed| trashed.item.id).collect();
+				app.forget(&purged);
+				app.write()?;
+			}
+		}
+	}
+	Ok(())
+}
+
+// Index of the entry with this id, a unique prefix of it is enough
+fn position(app: &App, id: &str) -> Result<usize> {
+	find(app.items.iter(), id)
+}
+
+fn find<'a>(items: impl Iterator<Item = &'a Password>, id: &str) -> Result<usize> {
+	let id = id.to_ascii_lowercase();
+	let ids: Vec<String> = items.map(Password::id).collect();
+	if let Some(index) = ids.iter().position(|item| *item == id) {
+		return Ok(index);
+	}
+
+	let matches: Vec<usize> = (0..ids.len())
+		.filter(|&index| !id.is_empty() && ids[index].starts_with(&id))
+		.collect();
+	match matches[..] {
+		[index] => Ok(index),
+		[] => bail!(CliError::NotFound(id)),
+		_ => bail!("Id {id} matches {} entries, give more of it.", matches.len()),
+	}
+}
+
+// Duplicates get their own exit code
+fn check_duplicate(items: &[Password], skip: Option<usize>, service: &str, login: &str) -> Result<()> {
+	if is_duplicate(items, skip, service, login) {
+		bail!(CliError::Duplicate {
+			service: service.into(),
+			login: login.into(),
+		});
+	}
+	Ok(())
+}
+
+// "-" means the secret comes from stdin, so it doesn't end up in shell history
+fn read_secret(value: String) -> Result<String> {
+	if value != "-" {
+		return Ok(value);
+	}
+
+	let mut line = String::new();
+	io::stdin().read_line(&mut line)?;
+	Ok(line.trim_end_matches(['\r', '\n']).into())
+}
+
+#[cfg(test)]
+mod test_cli {
+	use super::{Cli, CliError, Status, check_duplicate};
+	use crate::app::Password;
+	use clap::CommandFactory;
+
+	#[test]
+	fn verify_cli() {
+		Cli::command().debug_assert();
+	}
+
+	#[test]
+	fn duplicate_entry() {
+		let items = [Password::new("a", "b", "c")];
+
+		let err = check_duplicate(&items, None, "a", "b").unwrap_err();
+		assert_eq!(Status::from(&err), Status::Duplicate);
+		assert!(check_duplicate(&items, Some(0), "a", "b").is_ok());
+	}
+
+	#[test]
+	fn exit_codes() {
+		assert_eq!(Status::from(&CliError::NotFound("1".into()).into()), Status::NotFound);
+		assert_eq!(Status::from(&anyhow::anyhow!("other")), Status::Failure);
+	}
+}
@@ -0,0 +1,328 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Secrets on the system clipboard, cleared again by a detached helper process so the clear
+// still happens after psu exits
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use clap::ValueEnum;
+use clipboard::{ClipboardContext, ClipboardProvider};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+	env,
+	fs::OpenOptions,
+	io::{self, Write},
+	process::{Command, Stdio},
+	thread,
+	time::{Duration, Instant},
+};
+
+// Hidden subcommand the helper process runs
+pub const CLEAR_COMMAND: &str = "clear-clipboard";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+	// First one that fits the environment
+	#[default]
+	Auto,
+	// X11, macOS and Windows clipboard through the clipboard crate
+	Native,
+	// wl-copy and wl-paste
+	Wayland,
+	// Escape sequence asking the terminal to set its clipboard, works over SSH
+	Osc52,
+	// tmux paste buffer
+	Tmux,
+	// copy_command and paste_command from the config
+	Command,
+}
+
+pub trait Provider {
+	fn set(&mut self, text: &str) -> Result<()>;
+
+	// None when the backend can't read the clipboard back
+	fn get(&mut self) -> Result<Option<String>>;
+
+	fn clear(&mut self) -> Result<()> {
+		self.set("")
+	}
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Clipboard {
+	// Seconds until a copied secret is cleared, 0 leaves it on the clipboard
+	pub clear_after: u64,
+	pub backend: Backend,
+	// Program and arguments reading the text from stdin, for the command backend
+	pub copy_command: Vec<String>,
+	// Program and arguments printing the clipboard, optional
+	pub paste_command: Vec<String>,
+}
+
+impl Default for Clipboard {
+	fn default() -> Self {
+		Self {
+			clear_after: 30,
+			backend: Backend::default(),
+			copy_command: Vec::new(),
+			paste_command: Vec::new(),
+		}
+	}
+}
+
+impl Clipboard {
+	// Copy `text` and start the helper that clears it, returns when that will happen
+	pub fn copy(&self, text: &str) -> Result<Option<Instant>> {
+		let backend = self.resolve();
+		self.provider(backend)?.set(text)?;
+		if self.clear_after == 0 {
+			return Ok(None);
+		}
+
+		let mut command = Command::new(env::current_exe()?);
+		command
+			.arg(CLEAR_COMMAND)
+			.arg(self.clear_after.to_string())
+			.arg(backend.to_possible_value().unwrap().get_name())
+			.stdin(Stdio::piped())
+			.stdout(Stdio::null())
+			.stderr(Stdio::null());
+		// Own process group, so Ctrl-C in the terminal doesn't take the helper down too
+		#[cfg(unix)]
+		std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+		// Only a hash goes to the helper, never the secret itself
+		let mut child = command.spawn()?;
+		if let Some(mut stdin) = child.stdin.take() {
+			stdin.write_all(digest(text).as_bytes())?;
+		}
+		thread::spawn(move || child.wait());
+
+		Ok(Some(Instant::now() + Duration::from_secs(self.clear_after)))
+	}
+
+	// Body of the helper: wait, then clear the clipboard if it still holds what psu put
+	// there. Backends that can't read the clipboard are cleared regardless.
+	pub fn clear_later(&self, after: u64, backend: Backend) -> Result<()> {
+		let mut expected = String::new();
+		io::stdin().read_line(&mut expected)?;
+		thread::sleep(Duration::from_secs(after));
+
+		let mut provider = self.provider(backend)?;
+		match provider.get() {
+			Ok(Some(current)) if digest(&current) != expected.trim() => Ok(()),
+			Ok(_) => provider.clear(),
+			// Nothing to read usually means nothing to clear
+			Err(_) => Ok(()),
+		}
+	}
+
+	fn resolve(&self) -> Backend {
+		if self.backend != Backend::Auto {
+			return self.backend;
+		}
+
+		let set = |name: &str| env::var_os(name).is_some_and(|value| !value.is_empty());
+		if !self.copy_command.is_empty() {
+			Backend::Command
+		} else if set("WAYLAND_DISPLAY") && on_path("wl-copy") {
+			Backend::Wayland
+		} else if set("DISPLAY") || cfg!(any(target_os = "macos", windows)) {
+			Backend::Native
+		} else if set("TMUX") {
+			Backend::Tmux
+		} else {
+			Backend::Osc52
+		}
+	}
+
+	fn provider(&self, backend: Backend) -> Result<Box<dyn Provider>> {
+		Ok(match backend {
+			Backend::Auto => self.provider(self.resolve())?,
+			Backend::Native => Box::new(Native(
+				ClipboardContext::new().map_err(|err| anyhow!("Clipboard isn't available: {err}"))?,
+			)),
+			Backend::Wayland => Box::new(Wayland),
+			Backend::Osc52 => Box::new(Osc52 {
+				tmux: env::var_os("TMUX").is_some(),
+			}),
+			Backend::Tmux => Box::new(Tmux),
+			Backend::Command => {
+				if self.copy_command.is_empty() {
+					bail!("Clipboard backend \"command\" needs copy_command in config.toml.");
+				}
+				Box::new(Custom {
+					copy: self.copy_command.clone(),
+					paste: self.paste_command.clone(),
+				})
+			}
+		})
+	}
+}
+
+struct Native(ClipboardContext);
+
+impl Provider for Native {
+	fn set(&mut self, text: &str) -> Result<()> {
+		self.0
+			.set_contents(text.into())
+			.map_err(|err| anyhow!("Failed to copy: {err}"))
+	}
+
+	fn get(&mut self) -> Result<Option<String>> {
+		self.0
+			.get_contents()
+			.map(Some)
+			.map_err(|err| anyhow!("Failed to read the clipboard: {err}"))
+	}
+}
+
+struct Wayland;
+
+impl Provider for Wayland {
+	fn set(&mut self, text: &str) -> Result<()> {
+		pipe_to(&["wl-copy"], text)
+	}
+
+	fn get(&mut self) -> Result<Option<String>> {
+		read_from(&["wl-paste", "--no-newline"]).map(Some)
+	}
+
+	fn clear(&mut self) -> Result<()> {
+		pipe_to(&["wl-copy", "--clear"], "")
+	}
+}
+
+struct Osc52 {
+	// Inside tmux the sequence has to be passed through to the outer terminal
+	tmux: bool,
+}
+
+impl Provider for Osc52 {
+	fn set(&mut self, text: &str) -> Result<()> {
+		let mut tty = OpenOptions::new()
+			.write(true)
+			.open("/dev/tty")
+			.context("No terminal to send the OSC 52 sequence to.")?;
+		tty.write_all(osc52(text, self.tmux).as_bytes())?;
+		Ok(tty.flush()?)
+	}
+
+	fn get(&mut self) -> Result<Option<String>> {
+		Ok(None)
+	}
+}
+
+struct Tmux;
+
+impl Provider for Tmux {
+	fn set(&mut self, text: &str) -> Result<()> {
+		// -w also forwards it to the system clipboard when tmux's set-clipboard allows it
+		pipe_to(&["tmux", "load-buffer", "-w", "-"], text)
+	}
+
+	fn get(&mut self) -> Result<Option<String>> {
+		read_from(&["tmux", "save-buffer", "-"]).map(Some)
+	}
+
+	fn clear(&mut self) -> Result<()> {
+		pipe_to(&["tmux", "delete-buffer"], "")
+	}
+}
+
+struct Custom {
+	copy: Vec<String>,
+	paste: Vec<String>,
+}
+
+impl Provider for Custom {
+	fn set(&mut self, text: &str) -> Result<()> {
+		pipe_to(&self.copy, text)
+	}
+
+	fn get(&mut self) -> Result<Option<String>> {
+		match self.paste.is_empty() {
+			true => Ok(None),
+			false => read_from(&self.paste).map(Some),
+		}
+	}
+}
+
+fn osc52(text: &str, tmux: bool) -> String {
+	let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+	match tmux {
+		true => format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b")),
+		false => sequence,
+	}
+}
+
+// Output isn't captured: copy tools like wl-copy and xclip stay in the background holding it
+fn pipe_to<T: AsRef<str>>(program: &[T], input: &str) -> Result<()> {
+	let name = program[0].as_ref();
+	let mut child = Command::new(name)
+		.args(program[1..].iter().map(AsRef::as_ref))
+		.stdin(Stdio::piped())
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.spawn()
+		.with_context(|| format!("Failed to run {name}."))?;
+	if let Some(mut stdin) = child.stdin.take() {
+		stdin.write_all(input.as_bytes())?;
+	}
+
+	if !child.wait()?.success() {
+		bail!("{name} failed to copy.");
+	}
+	Ok(())
+}
+
+fn read_from<T: AsRef<str>>(program: &[T]) -> Result<String> {
+	let name = program[0].as_ref();
+	let output = Command::new(name)
+		.args(program[1..].iter().map(AsRef::as_ref))
+		.stdin(Stdio::null())
+		.stderr(Stdio::null())
+		.output()
+		.with_context(|| format!("Failed to run {name}."))?;
+
+	if !output.status.success() {
+		bail!("{name} failed to read the clipboard.");
+	}
+	Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn on_path(program: &str) -> bool {
+	env::var_os("PATH").is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+fn digest(text: &str) -> String {
+	Sha256::digest(text.as_bytes())
+		.iter()
+		.map(|byte| format!("{byte:02x}"))
+		.collect()
+}
+
+#[cfg(test)]
+mod test_clipboard {
+	use super::{Backend, Clipboard, osc52};
+
+	#[test]
+	fn osc52_sequence() {
+		assert_eq!(osc52("hunter2", false), "\x1b]52;c;aHVudGVyMg==\x07");
+		assert_eq!(osc52("hunter2", true), "\x1bPtmux;\x1b\x1b]52;c;aHVudGVyMg==\x07\x1b\\");
+	}
+
+	#[test]
+	fn command_backend() {
+		let clipboard: Clipboard = toml::from_str("copy_command = [\"xclip\", \"-i\"]").unwrap();
+		assert_eq!(clipboard.backend, Backend::Auto);
+		assert_eq!(clipboard.resolve(), Backend::Command);
+
+		let clipboard = Clipboard {
+			backend: Backend::Command,
+			..Default::default()
+		};
+		assert!(clipboard.provider(Backend::Command).is_err());
+	}
+}
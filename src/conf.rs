@@ -1,9 +1,12 @@
 // #![allow(unused)]
 // #![warn(clippy::all, clippy::pedantic)]
+use crate::clipboard::Clipboard;
+use crate::generator::{Generator, Passphrase};
 use anyhow::{Context, Result};
 use config::{Config as ConfigBuilder, ConfigError, File, FileFormat};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::{fs, io::ErrorKind, path::PathBuf};
+use toml_edit::{DocumentMut, value};
 
 const CONFIG_FILE: &str = "config.toml";
 const APP_NAME: &str = "psu";
@@ -11,7 +14,27 @@ const APP_NAME: &str = "psu";
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Config {
 	pub path: PathBuf,
+	// Have I Been Pwned SHA-1 file or range directory for the breach check
+	pub breaches: Option<PathBuf>,
 	pub fields: Fields,
+	#[serde(default)]
+	pub generator: Generator,
+	#[serde(default)]
+	pub passphrase: Passphrase,
+	#[serde(default)]
+	pub clipboard: Clipboard,
+	#[serde(default)]
+	pub table: Table,
+	#[serde(default)]
+	pub form: Form,
+	#[serde(default)]
+	pub expiry: Expiry,
+	#[serde(default)]
+	pub audit: Audit,
+	#[serde(default)]
+	pub trash: Trash,
+	#[serde(default)]
+	pub undo: Undo,
 }
 
 #[derive(Deserialize, Default, Serialize, Debug)]
@@ -21,6 +44,143 @@ pub struct Fields {
 	service: String,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Table {
+	// Show passwords instead of bullets from the start
+	pub reveal_passwords: bool,
+	// Row order, (s) and (S) in the table change it and save it back here
+	pub sort: SortKey,
+	pub sort_descending: bool,
+	// Folders and tags next to the table, (F) hides it for the session
+	pub sidebar: bool,
+}
+
+impl Default for Table {
+	fn default() -> Self {
+		Self {
+			reveal_passwords: false,
+			sort: SortKey::default(),
+			sort_descending: false,
+			sidebar: true,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+	// Order the entries were added in
+	#[default]
+	Added,
+	Service,
+	Login,
+	Modified,
+	Strength,
+}
+
+impl SortKey {
+	pub fn next(self) -> Self {
+		match self {
+			SortKey::Added => SortKey::Service,
+			SortKey::Service => SortKey::Login,
+			SortKey::Login => SortKey::Modified,
+			SortKey::Modified => SortKey::Strength,
+			SortKey::Strength => SortKey::Added,
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			SortKey::Added => "added",
+			SortKey::Service => "service",
+			SortKey::Login => "login",
+			SortKey::Modified => "modified",
+			SortKey::Strength => "strength",
+		}
+	}
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Form {
+	// New entries need the password typed twice
+	pub confirm_password: bool,
+	// Replaced passwords kept per entry, 0 keeps none
+	pub password_history: usize,
+}
+
+impl Default for Form {
+	fn default() -> Self {
+		Self {
+			confirm_password: true,
+			password_history: 10,
+		}
+	}
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Expiry {
+	// Entries expiring within this many days are highlighted already
+	pub warn_days: u64,
+	// Passwords unchanged for longer count as expired, 0 turns it off
+	pub max_age_days: u64,
+}
+
+impl Default for Expiry {
+	fn default() -> Self {
+		Self {
+			warn_days: 14,
+			max_age_days: 0,
+		}
+	}
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Audit {
+	// Passwords unchanged for this many days are reported as old, 0 turns it off
+	pub old_days: u64,
+}
+
+impl Default for Audit {
+	fn default() -> Self {
+		Self { old_days: 365 }
+	}
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Trash {
+	// Deleted entries are purged for good after this many days, 0 keeps them
+	pub retention_days: u64,
+}
+
+impl Default for Trash {
+	fn default() -> Self {
+		Self { retention_days: 30 }
+	}
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Undo {
+	// Changes that can be undone, older ones are forgotten
+	pub limit: usize,
+	// Keep the history in the vault, so changes can be undone after a restart
+	pub persist: bool,
+}
+
+impl Default for Undo {
+	fn default() -> Self {
+		Self {
+			limit: 50,
+			persist: false,
+		}
+	}
+}
+
 impl Config {
 	pub fn new() -> Result<Self, ConfigError> {
 		let mut builder = ConfigBuilder::builder();
@@ -49,9 +209,34 @@ impl Config {
 		Ok(())
 	}
 
+	// Write the table's sort order to config.toml, the rest of the file stays untouched
+	pub fn save_sort(&self) -> Result<()> {
+		let path = Self::get_app_data_dir()
+			.context("Failed to obtain config directory.")?
+			.join(CONFIG_FILE);
+		let mut conf: DocumentMut = match fs::read_to_string(&path) {
+			Ok(text) => text.parse().context("Failed to parse configuration.")?,
+			Err(err) if err.kind() == ErrorKind::NotFound => DocumentMut::new(),
+			Err(err) => return Err(err).context("Failed to read config."),
+		};
+
+		let table = conf.entry("table").or_insert(toml_edit::table());
+		table["sort"] = value(self.table.sort.name());
+		table["sort_descending"] = value(self.table.sort_descending);
+		fs::write(path, conf.to_string()).context("Failed write config.")?;
+		Ok(())
+	}
+
 	fn replace_tilde(&mut self) {
 		if let Some(home) = dirs::home_dir() {
-			self.path = self.path.to_string_lossy().replace('~', &home.to_string_lossy()).into();
+			let home = home.to_string_lossy();
+			self.path = self.path.to_string_lossy().replace('~', &home).into();
+			for path in [&mut self.passphrase.wordlist, &mut self.breaches]
+				.into_iter()
+				.flatten()
+			{
+				*path = path.to_string_lossy().replace('~', &home).into();
+			}
 		}
 	}
 
@@ -64,14 +249,24 @@ impl Default for Config {
 	fn default() -> Self {
 		Self {
 			path: dirs::home_dir().unwrap_or_default(),
+			breaches: None,
 			fields: Fields::default(),
+			generator: Generator::default(),
+			passphrase: Passphrase::default(),
+			clipboard: Clipboard::default(),
+			table: Table::default(),
+			form: Form::default(),
+			expiry: Expiry::default(),
+			audit: Audit::default(),
+			trash: Trash::default(),
+			undo: Undo::default(),
 		}
 	}
 }
 
 #[cfg(test)]
 mod test_config {
-	use super::Config;
+	use super::{Config, SortKey, Table};
 
 	#[test]
 	fn rep_tilde() {
@@ -85,4 +280,12 @@ mod test_config {
 			assert_eq!(conf.path, home.join("Downloads"))
 		}
 	}
+
+	#[test]
+	fn sort_order() {
+		let table: Table = toml::from_str("sort = \"strength\"\nsort_descending = true").unwrap();
+		assert_eq!((table.sort, table.sort_descending), (SortKey::Strength, true));
+		assert_eq!(table.sort.next(), SortKey::Added);
+		assert_eq!(Table::default().sort, SortKey::Added);
+	}
 }
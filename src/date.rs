@@ -0,0 +1,98 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Unix timestamps and the YYYY-MM-DD dates they are shown and entered as, all in UTC
+use anyhow::{Context, Result, bail};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DAY: u64 = 24 * 60 * 60;
+
+pub fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs()
+}
+
+// Midnight at the start of the date
+pub fn parse(text: &str) -> Result<u64> {
+	let invalid = || format!("Date \"{text}\" must look like 2030-12-31.");
+	let parts: Vec<&str> = text.trim().split('-').collect();
+	let [year, month, day] = parts[..] else {
+		bail!(invalid());
+	};
+	let (year, month, day): (i64, u32, u32) = (
+		year.parse().with_context(invalid)?,
+		month.parse().with_context(invalid)?,
+		day.parse().with_context(invalid)?,
+	);
+	// Keeps the day arithmetic below from overflowing
+	if !(1970..=9999).contains(&year) {
+		bail!(invalid());
+	}
+
+	// Days past the end of the month roll over, so they don't survive the round trip
+	let days = days_from_civil(year, month, day);
+	if !(1..=12).contains(&month) || civil_from_days(days) != (year, month, day) {
+		bail!(invalid());
+	}
+	Ok(days as u64 * DAY)
+}
+
+pub fn format(time: u64) -> String {
+	let (year, month, day) = civil_from_days((time / DAY) as i64);
+	format!("{year:04}-{month:02}-{day:02}")
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date, from Howard Hinnant's date algorithms
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+	let days = days + 719_468;
+	let era = days.div_euclid(146_097);
+	let day_of_era = days - era * 146_097;
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month = (5 * day_of_year + 2) / 153;
+	let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+	let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+	let year = year_of_era + era * 400;
+	(if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod test_date {
+	use super::{DAY, format, parse};
+
+	#[test]
+	fn round_trip() {
+		assert_eq!(parse("1970-01-01").unwrap(), 0);
+		assert_eq!(parse("2000-03-01").unwrap(), 951_868_800);
+		assert_eq!(parse("2024-02-29").unwrap(), 1_709_164_800);
+		assert_eq!(format(1_709_164_800 + DAY - 1), "2024-02-29");
+		assert_eq!(format(parse("2099-12-31").unwrap()), "2099-12-31");
+		assert_eq!(format(parse("9999-12-31").unwrap()), "9999-12-31");
+	}
+
+	#[test]
+	fn invalid_dates() {
+		for text in [
+			"2023-02-29",
+			"2023-13-01",
+			"2023-00-10",
+			"1969-12-31",
+			"10000-01-01",
+			"300000000000000-01-01",
+			"-9223372036854775808-01-01",
+			"2023-1",
+			"next week",
+		] {
+			assert!(parse(text).is_err(), "{text}");
+		}
+	}
+}
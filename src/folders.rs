@@ -0,0 +1,162 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Folders and tags of the sidebar. A folder is a "/" separated path like "work/acme", an
+// entry sits in one folder and counts towards every folder above it too.
+use crate::app::Password;
+use std::collections::BTreeMap;
+
+pub const SEPARATOR: char = '/';
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Node {
+	#[default]
+	All,
+	Folder(String),
+	// Entries outside of every folder
+	Unfiled,
+	Tag(String),
+}
+
+impl Node {
+	pub fn contains(&self, item: &Password) -> bool {
+		match self {
+			Node::All => true,
+			Node::Folder(path) => in_folder(&item.folder, path),
+			Node::Unfiled => item.folder.is_empty(),
+			Node::Tag(tag) => item.tags.contains(tag),
+		}
+	}
+
+	pub fn name(&self) -> &str {
+		match self {
+			Node::All => "All",
+			Node::Folder(path) => path,
+			Node::Unfiled => "No folder",
+			Node::Tag(tag) => tag,
+		}
+	}
+}
+
+// Line of the sidebar
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+	pub node: Node,
+	// Nesting of folders, 0 for everything else
+	pub depth: usize,
+	pub count: usize,
+}
+
+impl Entry {
+	// Folders only show their last part, the nesting tells the rest
+	pub fn label(&self) -> String {
+		match &self.node {
+			Node::Folder(path) => path.rsplit(SEPARATOR).next().unwrap_or_default().into(),
+			Node::Tag(tag) => format!("#{tag}"),
+			node => node.name().into(),
+		}
+	}
+}
+
+// `folder` is `path` or somewhere below it
+pub fn in_folder(folder: &str, path: &str) -> bool {
+	folder
+		.strip_prefix(path)
+		.is_some_and(|rest| rest.is_empty() || rest.starts_with(SEPARATOR))
+}
+
+// " work//acme/ " becomes "work/acme"
+pub fn normalize(folder: &str) -> String {
+	folder
+		.split(SEPARATOR)
+		.map(str::trim)
+		.filter(|part| !part.is_empty())
+		.collect::<Vec<_>>()
+		.join(&SEPARATOR.to_string())
+}
+
+// Every entry first, then the folder tree depth first and the tags, both alphabetical.
+// "No folder" only shows up once there are folders.
+pub fn tree(items: &[Password]) -> Vec<Entry> {
+	let mut folders: BTreeMap<Vec<&str>, usize> = BTreeMap::new();
+	let mut tags: BTreeMap<&str, usize> = BTreeMap::new();
+	for item in items {
+		let parts: Vec<&str> = item.folder.split(SEPARATOR).filter(|part| !part.is_empty()).collect();
+		for depth in 1..=parts.len() {
+			*folders.entry(parts[..depth].to_vec()).or_default() += 1;
+		}
+		for tag in &item.tags {
+			*tags.entry(tag).or_default() += 1;
+		}
+	}
+
+	let mut tree = vec![Entry {
+		node: Node::All,
+		depth: 0,
+		count: items.len(),
+	}];
+	tree.extend(folders.into_iter().map(|(parts, count)| Entry {
+		node: Node::Folder(parts.join(&SEPARATOR.to_string())),
+		depth: parts.len() - 1,
+		count,
+	}));
+	let unfiled = items.iter().filter(|item| item.folder.is_empty()).count();
+	if tree.len() > 1 && unfiled > 0 {
+		tree.push(Entry {
+			node: Node::Unfiled,
+			depth: 0,
+			count: unfiled,
+		});
+	}
+	tree.extend(tags.into_iter().map(|(tag, count)| Entry {
+		node: Node::Tag(tag.into()),
+		depth: 0,
+		count,
+	}));
+	tree
+}
+
+#[cfg(test)]
+mod test_folders {
+	use super::{Node, in_folder, normalize, tree};
+	use crate::app::Password;
+
+	#[test]
+	fn paths() {
+		assert_eq!(normalize(" work//acme/ "), "work/acme");
+		assert_eq!(normalize("/"), "");
+		assert!(in_folder("work/acme", "work"));
+		assert!(in_folder("work", "work"));
+		assert!(!in_folder("workshop", "work"));
+	}
+
+	#[test]
+	fn counts() {
+		let mut items = [
+			Password::new("a", "a", "a"),
+			Password::new("b", "b", "b"),
+			Password::new("c", "c", "c"),
+			Password::new("d", "d", "d"),
+		];
+		items[0].folder = "work/acme".into();
+		items[1].folder = "work".into();
+		items[2].folder = "work-old".into();
+		items[3].tags = vec!["mail".into()];
+
+		let rows: Vec<(String, usize, usize)> = tree(&items)
+			.iter()
+			.map(|entry| (entry.label(), entry.depth, entry.count))
+			.collect();
+		let expected = [
+			("All", 0, 4),
+			("work", 0, 2),
+			("acme", 1, 1),
+			("work-old", 0, 1),
+			("No folder", 0, 1),
+			("#mail", 0, 1),
+		];
+		assert_eq!(rows, expected.map(|(label, depth, count)| (label.into(), depth, count)));
+
+		assert!(Node::Folder("work".into()).contains(&items[0]));
+		assert!(!Node::Folder("work".into()).contains(&items[2]));
+		assert!(Node::Unfiled.contains(&items[3]));
+	}
+}
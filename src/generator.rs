@@ -0,0 +1,240 @@
+// #![warn(clippy::all, clippy::pedantic)]
+use anyhow::{Context, Result, bail};
+use rand::{Rng, rngs::OsRng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fs, path::PathBuf};
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+// EFF large word list, https://www.eff.org/dice (CC BY 3.0)
+pub const EFF_WORDLIST: &str = include_str!("../assets/eff_large_wordlist.txt");
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Generator {
+	pub length: usize,
+	pub lowercase: bool,
+	pub uppercase: bool,
+	pub digits: bool,
+	pub symbols: bool,
+	// Characters never used, by default the ones easy to confuse with each other
+	pub exclude: String,
+	// Every enabled class must appear at least once
+	pub require_every_class: bool,
+}
+
+impl Default for Generator {
+	fn default() -> Self {
+		Self {
+			length: 20,
+			lowercase: true,
+			uppercase: true,
+			digits: true,
+			symbols: true,
+			exclude: "Il1O0o|`'\"".into(),
+			require_every_class: true,
+		}
+	}
+}
+
+impl Generator {
+	// Bits of entropy, ignoring the small loss from requiring every class
+	pub fn entropy(&self) -> f64 {
+		self.length as f64 * (self.classes().concat().len() as f64).log2()
+	}
+
+	pub fn generate(&self) -> Result<String> {
+		let classes = self.classes();
+		if classes.is_empty() {
+			bail!("Password generator has no characters to choose from.");
+		}
+		if self.require_every_class && self.length < classes.len() {
+			bail!(
+				"Password length {} is too short to contain all {} character classes.",
+				self.length,
+				classes.len()
+			);
+		}
+
+		let all: Vec<char> = classes.concat();
+		let mut password: Vec<char> = Vec::with_capacity(self.length);
+		if self.require_every_class {
+			password.extend(classes.iter().map(|class| pick(class)));
+		}
+		while password.len() < self.length {
+			password.push(pick(&all));
+		}
+		password.shuffle(&mut OsRng);
+
+		Ok(password.into_iter().collect())
+	}
+
+	// Enabled character classes without excluded characters, empty classes are dropped
+	fn classes(&self) -> Vec<Vec<char>> {
+		[
+			(self.lowercase, LOWERCASE),
+			(self.uppercase, UPPERCASE),
+			(self.digits, DIGITS),
+			(self.symbols, SYMBOLS),
+		]
+		.into_iter()
+		.filter(|(enabled, _)| *enabled)
+		.map(|(_, chars)| chars.chars().filter(|c| !self.exclude.contains(*c)).collect::<Vec<_>>())
+		.filter(|class| !class.is_empty())
+		.collect()
+	}
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Passphrase {
+	pub words: usize,
+	pub separator: String,
+	// Start every word with a capital letter
+	pub capitalize: bool,
+	// Append a random digit to one of the words
+	pub number: bool,
+	// One word per line, diceware style "11111 word" lines work too. Bundled EFF list if unset
+	pub wordlist: Option<PathBuf>,
+}
+
+impl Default for Passphrase {
+	fn default() -> Self {
+		Self {
+			words: 6,
+			separator: "-".into(),
+			capitalize: false,
+			number: false,
+			wordlist: None,
+		}
+	}
+}
+
+impl Passphrase {
+	pub fn load_words(&self) -> Result<Vec<String>> {
+		let text = match &self.wordlist {
+			Some(path) => {
+				fs::read_to_string(path).with_context(|| format!("Failed to read word list {}.", path.display()))?
+			}
+			None => EFF_WORDLIST.into(),
+		};
+
+		// Duplicates would make the entropy estimate lie
+		let words: BTreeSet<String> = text
+			.lines()
+			.filter_map(|line| line.split_whitespace().last())
+			.map(String::from)
+			.collect();
+		if words.len() < 2 {
+			bail!("Word list needs at least two different words.");
+		}
+
+		Ok(words.into_iter().collect())
+	}
+
+	// Bits of entropy for a word list of `list_len` words
+	pub fn entropy(&self, list_len: usize) -> f64 {
+		let mut bits = self.words as f64 * (list_len as f64).log2();
+		if self.number {
+			bits += 10f64.log2() + (self.words as f64).log2();
+		}
+		bits
+	}
+
+	// Passphrase and its entropy in bits
+	pub fn generate(&self) -> Result<(String, f64)> {
+		if self.words == 0 {
+			bail!("Passphrase needs at least one word.");
+		}
+
+		let list = self.load_words()?;
+		let mut words: Vec<String> = (0..self.words)
+			.map(|_| list[OsRng.gen_range(0..list.len())].clone())
+			.collect();
+
+		if self.capitalize {
+			for word in &mut words {
+				*word = capitalize(word);
+			}
+		}
+		if self.number {
+			let index = OsRng.gen_range(0..words.len());
+			words[index].push(char::from(b'0' + OsRng.gen_range(0..10)));
+		}
+
+		Ok((words.join(&self.separator), self.entropy(list.len())))
+	}
+}
+
+fn capitalize(word: &str) -> String {
+	let mut chars = word.chars();
+	match chars.next() {
+		Some(first) => first.to_uppercase().chain(chars).collect(),
+		None => String::new(),
+	}
+}
+
+fn pick(chars: &[char]) -> char {
+	chars[OsRng.gen_range(0..chars.len())]
+}
+
+#[cfg(test)]
+mod test_generator {
+	use super::{DIGITS, Generator, LOWERCASE, Passphrase, SYMBOLS, UPPERCASE};
+
+	#[test]
+	fn every_class() {
+		let generator = Generator::default();
+		for _ in 0..50 {
+			let password = generator.generate().unwrap();
+			assert_eq!(password.chars().count(), generator.length);
+			for class in [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS] {
+				assert!(password.chars().any(|c| class.contains(c)), "{password} misses {class}");
+			}
+			assert!(!password.chars().any(|c| generator.exclude.contains(c)));
+		}
+	}
+
+	#[test]
+	fn too_short() {
+		let generator = Generator {
+			length: 3,
+			..Default::default()
+		};
+		assert!(generator.generate().is_err());
+	}
+
+	#[test]
+	fn nothing_enabled() {
+		let generator = Generator {
+			digits: true,
+			exclude: "0123456789".into(),
+			lowercase: false,
+			uppercase: false,
+			symbols: false,
+			..Default::default()
+		};
+		assert!(generator.generate().is_err());
+	}
+
+	#[test]
+	fn eff_passphrase() {
+		let passphrase = Passphrase {
+			separator: " ".into(),
+			capitalize: true,
+			number: true,
+			..Default::default()
+		};
+		assert_eq!(passphrase.load_words().unwrap().len(), 7776);
+
+		let (phrase, entropy) = passphrase.generate().unwrap();
+		let words: Vec<&str> = phrase.split(' ').collect();
+		assert_eq!(words.len(), 6);
+		assert!(words.iter().all(|word| word.starts_with(char::is_uppercase)));
+		assert_eq!(phrase.chars().filter(char::is_ascii_digit).count(), 1);
+		// 6 * log2(7776) + log2(10) + log2(6)
+		assert!((entropy - 83.46).abs() < 0.01, "{entropy}");
+	}
+}
@@ -0,0 +1,176 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Undo and redo of vault changes. Every change keeps the entries and trash from before it,
+// so any mix of edits, deletes and imports rolls back the same way.
+use crate::app::Password;
+use crate::trash::Trashed;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Change {
+	// "delete github / me", shown when it's undone or redone
+	pub label: String,
+	pub items: Vec<Password>,
+	#[serde(default)]
+	pub trash: Vec<Trashed>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct History {
+	// Oldest first, the last one is undone next
+	#[serde(default)]
+	pub undo: Vec<Change>,
+	#[serde(default)]
+	pub redo: Vec<Change>,
+}
+
+impl History {
+	pub fn is_empty(&self) -> bool {
+		self.undo.is_empty() && self.redo.is_empty()
+	}
+
+	// Remember the state before a change, a new change drops what could be redone
+	pub fn record(&mut self, change: Change, limit: usize) {
+		self.undo.push(change);
+		self.redo.clear();
+		let extra = self.undo.len().saturating_sub(limit);
+		self.undo.drain(..extra);
+	}
+
+	// Swap `current` for the state before the last change, returns its label
+	pub fn undo(&mut self, current: &mut Change) -> Option<String> {
+		Self::swap(&mut self.undo, &mut self.redo, current)
+	}
+
+	pub fn redo(&mut self, current: &mut Change) -> Option<String> {
+		Self::swap(&mut self.redo, &mut self.undo, current)
+	}
+
+	// Purged entries go for good, out of every state that could be undone or redone too. A state
+	// left the same as its neighbour would undo to nothing, so it goes as well.
+	pub fn forget(&mut self, ids: &[Uuid], items: &[Password], trash: &[Trashed]) {
+		for change in self.undo.iter_mut().chain(&mut self.redo) {
+			change.items.retain(|item| !ids.contains(&item.id));
+			change.trash.retain(|trashed| !ids.contains(&trashed.item.id));
+		}
+		Self::dedup(&mut self.undo, items, trash);
+		Self::dedup(&mut self.redo, items, trash);
+	}
+
+	// Both stacks end next to the current state, walk them outwards from it
+	fn dedup(stack: &mut Vec<Change>, items: &[Password], trash: &[Trashed]) {
+		let mut kept: Vec<Change> = Vec::with_capacity(stack.len());
+		for change in stack.drain(..).rev() {
+			let (items, trash) = kept
+				.last()
+				.map_or((items, trash), |last| (&last.items[..], &last.trash[..]));
+			if change.items[..] != *items || change.trash[..] != *trash {
+				kept.push(change);
+			}
+		}
+		kept.reverse();
+		*stack = kept;
+	}
+
+	fn swap(from: &mut Vec<Change>, to: &mut Vec<Change>, current: &mut Change) -> Option<String> {
+		let mut change = from.pop()?;
+		current.label.clone_from(&change.label);
+		std::mem::swap(current, &mut change);
+		to.push(change);
+		Some(current.label.clone())
+	}
+}
+
+#[cfg(test)]
+mod test_history {
+	use super::{Change, History};
+	use crate::app::Password;
+	use crate::trash::Trashed;
+
+	fn state(services: &[&str]) -> Change {
+		Change {
+			label: String::new(),
+			items: services
+				.iter()
+				.map(|service| Password::new(*service, "me", "pw"))
+				.collect(),
+			trash: Vec::new(),
+		}
+	}
+
+	fn services(change: &Change) -> Vec<&str> {
+		change.items.iter().map(|item| item.service.as_str()).collect()
+	}
+
+	#[test]
+	fn undo_redo() {
+		let mut history = History::default();
+		history.record(
+			Change {
+				label: "add b".into(),
+				..state(&["a"])
+			},
+			10,
+		);
+		let mut current = state(&["a", "b"]);
+
+		assert_eq!(history.undo(&mut current).as_deref(), Some("add b"));
+		assert_eq!(services(&current), ["a"]);
+		assert_eq!(history.undo(&mut current), None);
+		assert_eq!(history.redo(&mut current).as_deref(), Some("add b"));
+		assert_eq!(services(&current), ["a", "b"]);
+
+		// Something new can't be redone past
+		history.undo(&mut current);
+		history.record(state(&["a"]), 10);
+		assert!(history.redo.is_empty());
+	}
+
+	#[test]
+	fn forget() {
+		let mut history = History::default();
+		let before = state(&["a", "b"]);
+		let purged = before.items[1].clone();
+		history.record(
+			Change {
+				label: "delete b".into(),
+				..before.clone()
+			},
+			10,
+		);
+		history.record(
+			Change {
+				label: "add c".into(),
+				items: before.items[..1].to_vec(),
+				trash: vec![Trashed {
+					item: purged.clone(),
+					deleted: 1,
+				}],
+			},
+			10,
+		);
+		let mut current = Change {
+			items: [&before.items[..1], &state(&["c"]).items[..]].concat(),
+			..Change::default()
+		};
+
+		// Deleting b undoes to nothing without it, so only adding c is left
+		history.forget(&[purged.id], &current.items, &current.trash);
+		assert_eq!(history.undo.len(), 1);
+		assert_eq!(history.undo(&mut current).as_deref(), Some("add c"));
+		assert_eq!(services(&current), ["a"]);
+		assert!(current.trash.is_empty());
+	}
+
+	#[test]
+	fn limit() {
+		let mut history = History::default();
+		for service in ["a", "b", "c"] {
+			history.record(state(&[service]), 2);
+		}
+		let oldest: Vec<Vec<&str>> = history.undo.iter().map(services).collect();
+		assert_eq!(oldest, [["b"], ["c"]]);
+	}
+}
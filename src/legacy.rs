@@ -0,0 +1,83 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Plaintext CSV storage used before the encrypted vault
+use crate::app::Password;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+	fs::{self, OpenOptions},
+	io::{self, Read},
+	path::Path,
+};
+
+pub const LEGACY_FILE: &str = "psu.csv";
+
+// Row of psu.csv, the numeric id was the position and gets replaced by a new one
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Row {
+	service: String,
+	login: String,
+	password: String,
+}
+
+pub fn read<T: AsRef<Path>>(path: T) -> Result<Vec<Password>> {
+	let path = path.as_ref();
+	let mut rdr = csv::Reader::from_path(path).with_context(|| format!("Failed to open {}.", path.display()))?;
+
+	rdr.deserialize::<Row>()
+		.enumerate()
+		.map(|(index, row)| {
+			// The CSV never recorded when anything changed, now would claim it just did
+			row.map(|row| Password {
+				created: None,
+				modified: None,
+				password_changed: None,
+				..Password::new(row.service, row.login, row.password)
+			})
+			.with_context(|| format!("Failed to parse row {} of {}.", index + 1, path.display()))
+		})
+		.collect()
+}
+
+// Overwrite the file with zeros before unlinking it. Journaling and copy-on-write
+// filesystems or SSD wear levelling may still keep old blocks around.
+pub fn secure_remove<T: AsRef<Path>>(path: T) -> Result<()> {
+	let path = path.as_ref();
+	let len = fs::metadata(path)?.len();
+	let mut file = OpenOptions::new().write(true).open(path)?;
+
+	io::copy(&mut io::repeat(0).take(len), &mut file)?;
+	file.sync_all()?;
+	drop(file);
+
+	fs::remove_file(path).with_context(|| format!("Failed to remove {}.", path.display()))
+}
+
+#[cfg(test)]
+mod test_legacy {
+	use super::{read, secure_remove};
+	use crate::vault::test_vault::temp_dir;
+
+	#[test]
+	fn read_csv() {
+		let path = temp_dir("read_csv").join(super::LEGACY_FILE);
+		std::fs::write(
+			&path,
+			"\"Id\",\"Service\",\"Login\",\"Password\"\n0,\"a\",\"b\",\"c, d\"\n",
+		)
+		.unwrap();
+
+		let items = read(&path).unwrap();
+		assert_eq!(items[0].ref_array()[1..], ["a", "b", "c, d"]);
+		assert_eq!(items[0].password_changed, None);
+	}
+
+	#[test]
+	fn remove_file() {
+		let path = temp_dir("remove_file").join(super::LEGACY_FILE);
+		std::fs::write(&path, "secret").unwrap();
+
+		secure_remove(&path).unwrap();
+		assert!(!path.exists());
+	}
+}
@@ -1,5 +1,7 @@
 // #![warn(clippy::all, clippy::pedantic)]
 use app::App;
+use clap::Parser;
+use cli::Cli;
 use color_eyre::Result;
 use crossterm::{
 	event::{DisableMouseCapture, EnableMouseCapture},
@@ -11,11 +13,32 @@ use run::run_app;
 use std::{error::Error, io};
 
 mod app;
+mod audit;
+mod breach;
+mod cli;
+mod clipboard;
 mod conf;
+mod date;
+mod folders;
+mod generator;
+mod history;
+mod legacy;
+mod otpauth;
+mod output;
+mod qr;
 mod run;
+mod search;
+mod strength;
+mod totp;
+mod trash;
 mod ui;
+mod vault;
 
 fn main() -> Result<(), Box<dyn Error>> {
+	if let Some(command) = Cli::parse().command {
+		std::process::exit(cli::run(command));
+	}
+
 	let mut app = App::new();
 
 	enable_raw_mode()?;
@@ -0,0 +1,225 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// otpauth:// URIs as shown in 2FA setup QR codes, and Google Authenticator
+// otpauth-migration:// exports that carry a whole batch of them
+use crate::app::{Data, Password};
+use crate::totp::{Algorithm, DEFAULT_DIGITS, DEFAULT_PERIOD, Totp};
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use percent_encoding::{NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
+use prost::Message;
+use url::Url;
+
+// Protobuf messages of otpauth-migration://offline?data=..., field numbers from the
+// Google Authenticator export format
+#[derive(Clone, PartialEq, Message)]
+struct MigrationPayload {
+	#[prost(message, repeated, tag = "1")]
+	otp_parameters: Vec<OtpParameters>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct OtpParameters {
+	#[prost(bytes = "vec", tag = "1")]
+	secret: Vec<u8>,
+	#[prost(string, tag = "2")]
+	name: String,
+	#[prost(string, tag = "3")]
+	issuer: String,
+	// 0 unspecified, 1 SHA1, 2 SHA256, 3 SHA512, 4 MD5
+	#[prost(int32, tag = "4")]
+	algorithm: i32,
+	// 0 unspecified, 1 six, 2 eight
+	#[prost(int32, tag = "5")]
+	digits: i32,
+	// 0 unspecified, 1 HOTP, 2 TOTP
+	#[prost(int32, tag = "6")]
+	otp_type: i32,
+}
+
+// Entries with their TOTP filled in and an empty password, one for an otpauth:// URI and
+// all of them for an otpauth-migration:// export
+pub fn parse(uri: &str) -> Result<Vec<Password>> {
+	let url = Url::parse(uri.trim()).context("Not a valid otpauth:// URI.")?;
+	match (url.scheme(), url.host_str()) {
+		("otpauth", Some("totp")) => Ok(vec![parse_totp(&url)?]),
+		("otpauth", Some("hotp")) => bail!("Counter based HOTP codes aren't supported, only TOTP."),
+		("otpauth-migration", _) => parse_migration(&url),
+		_ => bail!("Not an otpauth:// or otpauth-migration:// URI."),
+	}
+}
+
+fn parse_totp(url: &Url) -> Result<Password> {
+	let (mut secret, mut issuer) = (None, None);
+	let (mut algorithm, mut digits, mut period) = (Algorithm::default(), DEFAULT_DIGITS, DEFAULT_PERIOD);
+	for (key, value) in url.query_pairs() {
+		match key.to_ascii_lowercase().as_str() {
+			"secret" => secret = Some(value.into_owned()),
+			"issuer" => issuer = Some(value.into_owned()),
+			"algorithm" => {
+				algorithm = match value.to_ascii_uppercase().as_str() {
+					"SHA1" => Algorithm::Sha1,
+					"SHA256" => Algorithm::Sha256,
+					"SHA512" => Algorithm::Sha512,
+					_ => bail!("Unsupported TOTP algorithm {value}."),
+				}
+			}
+			"digits" => digits = value.parse().context("TOTP digits must be a number.")?,
+			"period" => period = value.parse().context("TOTP period must be a number.")?,
+			_ => {}
+		}
+	}
+
+	let secret = secret.context("otpauth:// URI has no secret.")?;
+	let totp = Totp::new(&secret, algorithm, digits, period)?;
+	let label = percent_decode_str(url.path().trim_start_matches('/')).decode_utf8_lossy();
+	Ok(entry(&label, issuer.as_deref(), totp))
+}
+
+fn parse_migration(url: &Url) -> Result<Vec<Password>> {
+	let data = url
+		.query_pairs()
+		.find(|(key, _)| key == "data")
+		.context("otpauth-migration:// URI has no data.")?
+		.1;
+	// Spaces are '+' signs that got decoded as form encoding
+	let payload = STANDARD
+		.decode(data.replace(' ', "+"))
+		.context("otpauth-migration:// data isn't valid base64.")?;
+	let payload = MigrationPayload::decode(payload.as_slice()).context("otpauth-migration:// data is damaged.")?;
+
+	payload
+		.otp_parameters
+		.into_iter()
+		.map(|otp| {
+			if otp.otp_type == 1 {
+				bail!("{} uses a counter based HOTP code, only TOTP is supported.", otp.name);
+			}
+			let algorithm = match otp.algorithm {
+				0 | 1 => Algorithm::Sha1,
+				2 => Algorithm::Sha256,
+				3 => Algorithm::Sha512,
+				_ => bail!("{} uses an unsupported algorithm.", otp.name),
+			};
+			let digits = if otp.digits == 2 { 8 } else { DEFAULT_DIGITS };
+
+			let totp = Totp::from_bytes(otp.secret, algorithm, digits, DEFAULT_PERIOD)?;
+			Ok(entry(&otp.name, Some(otp.issuer.as_str()), totp))
+		})
+		.collect()
+}
+
+// Labels are "Issuer:account" or just "account", the issuer parameter wins over the prefix
+fn entry(label: &str, issuer: Option<&str>, totp: Totp) -> Password {
+	let issuer = issuer.filter(|issuer| !issuer.is_empty());
+	// An issuer like "host:8080" has a ":" of its own, so a label starting with it loses it whole
+	let (prefix, account) = match issuer.and_then(|issuer| label.strip_prefix(issuer)?.strip_prefix(':')) {
+		Some(account) => (issuer, account.trim()),
+		None => match label.split_once(':') {
+			Some((prefix, account)) => (Some(prefix.trim()), account.trim()),
+			None => (None, label.trim()),
+		},
+	};
+	let service = issuer.or(prefix).unwrap_or(account);
+
+	let mut password = Password::new(service, account, "");
+	password.totp = Some(totp);
+	password
+}
+
+// otpauth:// URI of an entry's TOTP, service as issuer and login as account
+pub fn to_uri(item: &Password) -> Option<String> {
+	let totp = item.totp.as_ref()?;
+	let encode = |text: &str| utf8_percent_encode(text, NON_ALPHANUMERIC).to_string();
+
+	Some(format!(
+		"otpauth://totp/{}:{}?secret={}&issuer={}&algorithm={}&digits={}&period={}",
+		encode(item.service()),
+		encode(item.login()),
+		totp.secret(),
+		encode(item.service()),
+		totp.algorithm.name(),
+		totp.digits,
+		totp.period
+	))
+}
+
+#[cfg(test)]
+mod test_otpauth {
+	use super::{MigrationPayload, OtpParameters, parse, to_uri};
+	use crate::app::Password;
+	use crate::totp::{Algorithm, Totp};
+	use base64::{Engine, engine::general_purpose::STANDARD};
+	use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+	use prost::Message;
+
+	#[test]
+	fn totp_uri() {
+		let items = parse(
+			"otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60",
+		)
+		.unwrap();
+
+		assert_eq!(items[0].service, "ACME Co");
+		assert_eq!(items[0].login, "john.doe@email.com");
+		let totp = items[0].totp.as_ref().unwrap();
+		assert_eq!((totp.algorithm, totp.digits, totp.period), (Algorithm::Sha256, 8, 60));
+
+		assert!(parse("otpauth://hotp/a?secret=JBSWY3DPEHPK3PXP&counter=1").is_err());
+		assert!(parse("otpauth://totp/a").is_err());
+	}
+
+	#[test]
+	fn round_trip() {
+		for (service, login) in [("Git Lab", "me@example.com"), ("host:8080", "me:admin")] {
+			let mut item = Password::new(service, login, "");
+			item.totp = Some(Totp::new("JBSWY3DPEHPK3PXP", Algorithm::Sha512, 7, 45).unwrap());
+
+			let mut parsed = parse(&to_uri(&item).unwrap()).unwrap();
+			assert_ne!(parsed[0].id, item.id);
+			// A fresh entry, only the id and timestamps differ
+			parsed[0].id = item.id;
+			(parsed[0].created, parsed[0].modified, parsed[0].password_changed) =
+				(item.created, item.modified, item.password_changed);
+			assert_eq!(parsed, [item]);
+		}
+	}
+
+	#[test]
+	fn migration() {
+		let payload = MigrationPayload {
+			otp_parameters: vec![
+				OtpParameters {
+					secret: b"12345678901234567890".to_vec(),
+					name: "alice@example.com".into(),
+					issuer: "Example".into(),
+					algorithm: 1,
+					digits: 1,
+					otp_type: 2,
+				},
+				OtpParameters {
+					secret: b"abcdefghij".to_vec(),
+					name: "Other:bob".into(),
+					issuer: String::new(),
+					algorithm: 2,
+					digits: 2,
+					otp_type: 2,
+				},
+			],
+		};
+		let data = STANDARD.encode(payload.encode_to_vec());
+		let uri = format!(
+			"otpauth-migration://offline?data={}",
+			utf8_percent_encode(&data, NON_ALPHANUMERIC)
+		);
+
+		let items = parse(&uri).unwrap();
+		let entries: Vec<(&str, &str)> = items
+			.iter()
+			.map(|item| (item.service.as_str(), item.login.as_str()))
+			.collect();
+		assert_eq!(entries, [("Example", "alice@example.com"), ("Other", "bob")]);
+		// RFC 6238 SHA-1 vector, truncated to 6 digits
+		assert_eq!(items[0].totp.as_ref().unwrap().code_at(59), "287082");
+		assert_eq!(items[1].totp.as_ref().unwrap().digits, 8);
+	}
+}
@@ -0,0 +1,501 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Stable output formats for CLI queries
+use crate::app::{Data, Field, OldPassword, Password};
+use crate::date;
+use crate::strength::Strength;
+use crate::trash::Trashed;
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Format {
+	/// Columns separated by three spaces, for humans
+	#[default]
+	Plain,
+	/// One line per entry, tab separated, special characters escaped
+	Tsv,
+	/// JSON documented in README.md
+	Json,
+}
+
+// Public JSON shape of an entry, decoupled from the vault layout
+#[derive(Serialize)]
+struct Entry<'a> {
+	id: Uuid,
+	service: &'a str,
+	login: &'a str,
+	password: &'a str,
+	#[serde(skip_serializing_if = "str::is_empty")]
+	url: &'a str,
+	#[serde(skip_serializing_if = "str::is_empty")]
+	folder: &'a str,
+	#[serde(skip_serializing_if = "str::is_empty")]
+	notes: &'a str,
+	#[serde(skip_serializing_if = "<[_]>::is_empty")]
+	tags: &'a [String],
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	fields: Vec<EntryField<'a>>,
+	// Unix times
+	#[serde(skip_serializing_if = "Option::is_none")]
+	created: Option<u64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	modified: Option<u64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	password_changed: Option<u64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	expires: Option<u64>,
+	// Passwords it replaced, oldest first
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	old_passwords: Vec<EntryOldPassword<'a>>,
+}
+
+#[derive(Serialize)]
+struct EntryField<'a> {
+	name: &'a str,
+	value: &'a str,
+	secret: bool,
+}
+
+#[derive(Serialize)]
+struct EntryOldPassword<'a> {
+	password: &'a str,
+	replaced: u64,
+}
+
+impl<'a> From<&'a Password> for Entry<'a> {
+	fn from(value: &'a Password) -> Self {
+		Self {
+			id: value.id,
+			service: value.service(),
+			login: value.login(),
+			password: value.password(),
+			url: &value.url,
+			folder: &value.folder,
+			notes: &value.notes,
+			tags: &value.tags,
+			fields: value.fields.iter().map(EntryField::from).collect(),
+			created: value.created,
+			modified: value.modified,
+			password_changed: value.password_changed,
+			expires: value.expires,
+			old_passwords: value.old_passwords.iter().map(EntryOldPassword::from).collect(),
+		}
+	}
+}
+
+impl<'a> From<&'a OldPassword> for EntryOldPassword<'a> {
+	fn from(value: &'a OldPassword) -> Self {
+		Self {
+			password: &value.password,
+			replaced: value.replaced,
+		}
+	}
+}
+
+impl<'a> From<&'a Field> for EntryField<'a> {
+	fn from(value: &'a Field) -> Self {
+		Self {
+			name: &value.name,
+			value: &value.value,
+			secret: value.secret,
+		}
+	}
+}
+
+// Format a list of entries, JSON gets an array
+pub fn list(items: &[Password], format: Format) -> Result<String> {
+	Ok(match format {
+		Format::Json => serde_json::to_string_pretty(&items.iter().map(Entry::from).collect::<Vec<_>>())?,
+		_ => items
+			.iter()
+			.map(|item| line(item, format))
+			.collect::<Vec<_>>()
+			.join("\n"),
+	})
+}
+
+// Format a single entry, JSON gets an object
+pub fn single(item: &Password, format: Format) -> Result<String> {
+	Ok(match format {
+		Format::Json => serde_json::to_string_pretty(&Entry::from(item))?,
+		_ => line(item, format),
+	})
+}
+
+#[derive(Serialize)]
+struct TrashEntry<'a> {
+	#[serde(flatten)]
+	entry: EntryRef<'a>,
+	// Unix time
+	deleted: u64,
+}
+
+// Format the trash newest first, without the secrets
+pub fn trash(trash: &[Trashed], format: Format) -> Result<String> {
+	let entries: Vec<TrashEntry> = trash
+		.iter()
+		.rev()
+		.map(|trashed| TrashEntry {
+			entry: EntryRef::from(&trashed.item),
+			deleted: trashed.deleted,
+		})
+		.collect();
+
+	Ok(match format {
+		Format::Json => serde_json::to_string_pretty(&entries)?,
+		_ => entries
+			.iter()
+			.map(|trashed| {
+				let entry = &trashed.entry;
+				match format {
+					Format::Tsv => tsv(&[
+						&entry.id.to_string(),
+						entry.service,
+						entry.login,
+						&trashed.deleted.to_string(),
+					]),
+					_ => format!(
+						"{}   {}   {}   deleted {}",
+						entry.id,
+						entry.service,
+						entry.login,
+						date::format(trashed.deleted)
+					),
+				}
+			})
+			.collect::<Vec<_>>()
+			.join("\n"),
+	})
+}
+
+// Entry reference in audit reports, never carries the secret
+#[derive(Serialize)]
+struct EntryRef<'a> {
+	id: Uuid,
+	service: &'a str,
+	login: &'a str,
+}
+
+impl<'a> From<&'a Password> for EntryRef<'a> {
+	fn from(value: &'a Password) -> Self {
+		Self {
+			id: value.id,
+			service: value.service(),
+			login: value.login(),
+		}
+	}
+}
+
+#[derive(Serialize)]
+struct WeakEntry<'a> {
+	#[serde(flatten)]
+	entry: EntryRef<'a>,
+	score: &'static str,
+	warning: &'static str,
+}
+
+#[derive(Serialize)]
+struct OldEntry<'a> {
+	#[serde(flatten)]
+	entry: EntryRef<'a>,
+	// None when the entry never recorded a password change
+	days: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct BreachedEntry<'a> {
+	#[serde(flatten)]
+	entry: EntryRef<'a>,
+	count: u64,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	reused: Option<Vec<Vec<EntryRef<'a>>>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	weak: Option<Vec<WeakEntry<'a>>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	old: Option<Vec<OldEntry<'a>>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	breached: Option<Vec<BreachedEntry<'a>>>,
+}
+
+// Format `psu audit` results, a check that wasn't run is None
+pub fn audit(
+	items: &[Password],
+	reused: Option<&[Vec<usize>]>,
+	weak: Option<&[(usize, Strength)]>,
+	old: Option<&[(usize, Option<u64>)]>,
+	breached: Option<&[(usize, u64)]>,
+	format: Format,
+) -> Result<String> {
+	let report = Report {
+		reused: reused.map(|groups| {
+			groups
+				.iter()
+				.map(|group| group.iter().map(|index| EntryRef::from(&items[*index])).collect())
+				.collect()
+		}),
+		weak: weak.map(|weak| {
+			weak.iter()
+				.map(|(index, strength)| WeakEntry {
+					entry: EntryRef::from(&items[*index]),
+					score: strength.score.label(),
+					warning: strength.warning(),
+				})
+				.collect()
+		}),
+		old: old.map(|old| {
+			old.iter()
+				.map(|(index, days)| OldEntry {
+					entry: EntryRef::from(&items[*index]),
+					days: *days,
+				})
+				.collect()
+		}),
+		breached: breached.map(|breached| {
+			breached
+				.iter()
+				.map(|(index, count)| BreachedEntry {
+					entry: EntryRef::from(&items[*index]),
+					count: *count,
+				})
+				.collect()
+		}),
+	};
+
+	if format == Format::Json {
+		return Ok(serde_json::to_string_pretty(&report)?);
+	}
+
+	let mut lines = Vec::new();
+	for (group, entries) in report.reused.iter().flatten().enumerate() {
+		if format == Format::Plain {
+			lines.push(format!("Reused password #{} ({} entries):", group + 1, entries.len()));
+		}
+		for entry in entries {
+			lines.push(match format {
+				Format::Tsv => tsv(&[
+					"reused",
+					&(group + 1).to_string(),
+					&entry.id.to_string(),
+					entry.service,
+					entry.login,
+				]),
+				_ => format!("  {}   {}   {}", entry.id, entry.service, entry.login),
+			});
+		}
+	}
+	if format == Format::Plain && report.weak.as_ref().is_some_and(|weak| !weak.is_empty()) {
+		lines.push("Weak passwords:".into());
+	}
+	for weak in report.weak.iter().flatten() {
+		let entry = &weak.entry;
+		lines.push(match format {
+			Format::Tsv => tsv(&[
+				"weak",
+				weak.score,
+				&entry.id.to_string(),
+				entry.service,
+				entry.login,
+				weak.warning,
+			]),
+			_ => match weak.warning {
+				"" => format!("  {}   {}   {}   {}", entry.id, entry.service, entry.login, weak.score),
+				warning => format!(
+					"  {}   {}   {}   {}: {warning}",
+					entry.id, entry.service, entry.login, weak.score
+				),
+			},
+		});
+	}
+	if format == Format::Plain && report.old.as_ref().is_some_and(|old| !old.is_empty()) {
+		lines.push("Old passwords:".into());
+	}
+	for old in report.old.iter().flatten() {
+		let entry = &old.entry;
+		let days = old.days.map(|days| days.to_string()).unwrap_or_default();
+		lines.push(match format {
+			Format::Tsv => tsv(&["old", &days, &entry.id.to_string(), entry.service, entry.login]),
+			_ => match old.days {
+				Some(_) => format!(
+					"  {}   {}   {}   unchanged for {days} days",
+					entry.id, entry.service, entry.login
+				),
+				None => format!("  {}   {}   {}   unknown age", entry.id, entry.service, entry.login),
+			},
+		});
+	}
+	if format == Format::Plain && report.breached.as_ref().is_some_and(|breached| !breached.is_empty()) {
+		lines.push("Breached passwords:".into());
+	}
+	for breached in report.breached.iter().flatten() {
+		let entry = &breached.entry;
+		lines.push(match format {
+			Format::Tsv => tsv(&[
+				"breached",
+				&breached.count.to_string(),
+				&entry.id.to_string(),
+				entry.service,
+				entry.login,
+			]),
+			_ => format!(
+				"  {}   {}   {}   seen {} times",
+				entry.id, entry.service, entry.login, breached.count
+			),
+		});
+	}
+
+	Ok(lines.join("\n"))
+}
+
+fn tsv(fields: &[&str]) -> String {
+	fields
+		.iter()
+		.map(|field| escape_tsv(field))
+		.collect::<Vec<_>>()
+		.join("\t")
+}
+
+fn line(item: &Password, format: Format) -> String {
+	match format {
+		Format::Tsv => tsv(&item.ref_array().each_ref().map(String::as_str)),
+		_ => format!("{}   {item}", item.id),
+	}
+}
+
+fn escape_tsv(field: &str) -> String {
+	field
+		.replace('\\', "\\\\")
+		.replace('\t', "\\t")
+		.replace('\n', "\\n")
+		.replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod test_output {
+	use super::{Format, audit, list, single, trash};
+	use crate::app::{Field, OldPassword, Password};
+	use crate::trash::Trashed;
+	use uuid::Uuid;
+
+	// Entry with a predictable id and no timestamps
+	fn entry(id: u128, service: &str, login: &str, password: &str) -> Password {
+		Password {
+			id: Uuid::from_u128(id),
+			created: None,
+			modified: None,
+			password_changed: None,
+			..Password::new(service, login, password)
+		}
+	}
+
+	#[test]
+	fn tsv_escape() {
+		let item = entry(1, "a b", "c\td", "e\\f\n");
+		assert_eq!(
+			single(&item, Format::Tsv).unwrap(),
+			"00000000-0000-0000-0000-000000000001\ta b\tc\\td\te\\\\f\\n"
+		);
+	}
+
+	#[test]
+	fn json_list() {
+		let items = [entry(0, "a", "b", "c")];
+		let value: serde_json::Value = serde_json::from_str(&list(&items, Format::Json).unwrap()).unwrap();
+
+		assert_eq!(
+			value,
+			serde_json::json!([{
+				"id": "00000000-0000-0000-0000-000000000000",
+				"service": "a",
+				"login": "b",
+				"password": "c"
+			}])
+		);
+	}
+
+	#[test]
+	fn json_details() {
+		let mut item = entry(0, "a", "b", "c");
+		item.tags = vec!["work".into()];
+		item.folder = "work/acme".into();
+		item.expires = Some(1_924_905_600);
+		item.fields = vec![Field {
+			name: "pin".into(),
+			value: "1234".into(),
+			secret: true,
+		}];
+		item.old_passwords = vec![OldPassword {
+			password: "old".into(),
+			replaced: 86_400,
+		}];
+		let value: serde_json::Value = serde_json::from_str(&single(&item, Format::Json).unwrap()).unwrap();
+
+		assert_eq!(value["tags"], serde_json::json!(["work"]));
+		assert_eq!(value["folder"], "work/acme");
+		assert_eq!(
+			value["fields"],
+			serde_json::json!([{ "name": "pin", "value": "1234", "secret": true }])
+		);
+		assert_eq!(value["expires"], 1_924_905_600);
+		assert_eq!(
+			value["old_passwords"],
+			serde_json::json!([{ "password": "old", "replaced": 86_400 }])
+		);
+		assert!(value.get("url").is_none());
+	}
+
+	#[test]
+	fn trash_newest_first() {
+		let trashed = [1, 2].map(|id| Trashed {
+			item: entry(id, "a", "b", "hunter2"),
+			deleted: 86_400 * id as u64,
+		});
+		assert_eq!(
+			trash(&trashed, Format::Tsv).unwrap(),
+			[
+				"00000000-0000-0000-0000-000000000002\ta\tb\t172800",
+				"00000000-0000-0000-0000-000000000001\ta\tb\t86400",
+			]
+			.join("\n")
+		);
+		assert_eq!(
+			trash(&trashed[..1], Format::Plain).unwrap(),
+			"00000000-0000-0000-0000-000000000001   a   b   deleted 1970-01-02"
+		);
+		assert!(!trash(&trashed, Format::Json).unwrap().contains("hunter2"));
+	}
+
+	#[test]
+	fn audit_hides_passwords() {
+		let items = [entry(0, "a", "b", "hunter2"), entry(1, "c", "d", "hunter2")];
+		let groups = [vec![0, 1]];
+
+		for format in [Format::Plain, Format::Tsv, Format::Json] {
+			let report = audit(&items, Some(&groups), None, None, Some(&[(0, 12)]), format).unwrap();
+			assert!(!report.contains("hunter2"), "{report}");
+		}
+		assert_eq!(
+			audit(
+				&items,
+				Some(&groups),
+				None,
+				Some(&[(1, None)]),
+				Some(&[(0, 12)]),
+				Format::Tsv
+			)
+			.unwrap(),
+			[
+				"reused\t1\t00000000-0000-0000-0000-000000000000\ta\tb",
+				"reused\t1\t00000000-0000-0000-0000-000000000001\tc\td",
+				"old\t\t00000000-0000-0000-0000-000000000001\tc\td",
+				"breached\t12\t00000000-0000-0000-0000-000000000000\ta\tb",
+			]
+			.join("\n")
+		);
+	}
+}
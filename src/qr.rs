@@ -0,0 +1,102 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// QR codes: reading them from PNG screenshots and drawing them with block characters
+use anyhow::{Context, Result, bail};
+use png::{ColorType, Decoder, Transformations};
+use qrcode::{QrCode, render::unicode::Dense1x2};
+use std::{fs::File, path::Path};
+
+// Text of every QR code found in a PNG image
+pub fn decode_png<T: AsRef<Path>>(path: T) -> Result<Vec<String>> {
+	let path = path.as_ref();
+	let file = File::open(path).with_context(|| format!("Failed to open {}.", path.display()))?;
+	let mut decoder = Decoder::new(file);
+	decoder.set_transformations(Transformations::normalize_to_color8());
+	let mut reader = decoder
+		.read_info()
+		.with_context(|| format!("{} isn't a PNG image.", path.display()))?;
+	let mut buf = vec![0; reader.output_buffer_size()];
+	let info = reader.next_frame(&mut buf)?;
+
+	let channels = info.color_type.samples();
+	let has_alpha = matches!(info.color_type, ColorType::GrayscaleAlpha | ColorType::Rgba);
+	let luma = |x: usize, y: usize| {
+		let pixel = &buf[y * info.line_size + x * channels..];
+		let value = match channels {
+			1 | 2 => pixel[0] as u32,
+			_ => (pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000,
+		};
+		// Transparent pixels count as white paper
+		let value = match has_alpha {
+			true => {
+				let alpha = pixel[channels - 1] as u32;
+				(value * alpha + 255 * (255 - alpha)) / 255
+			}
+			false => value,
+		};
+		value as u8
+	};
+
+	let mut image = rqrr::PreparedImage::prepare_from_greyscale(info.width as usize, info.height as usize, luma);
+	let texts: Vec<String> = image
+		.detect_grids()
+		.into_iter()
+		.filter_map(|grid| grid.decode().ok())
+		.map(|(_, text)| text)
+		.collect();
+
+	if texts.is_empty() {
+		bail!("No QR code found in {}.", path.display());
+	}
+	Ok(texts)
+}
+
+// Dark modules are drawn as ink, so the text needs dark on light colours to scan
+pub fn render(text: &str) -> Result<Vec<String>> {
+	let code = QrCode::new(text).context("Text is too long for a QR code.")?;
+	Ok(code
+		.render::<Dense1x2>()
+		.quiet_zone(true)
+		.build()
+		.lines()
+		.map(String::from)
+		.collect())
+}
+
+#[cfg(test)]
+mod test_qr {
+	use super::decode_png;
+	use crate::vault::test_vault::temp_dir;
+	use png::{BitDepth, ColorType, Encoder};
+	use qrcode::{Color, QrCode};
+	use std::fs::File;
+
+	const SCALE: usize = 4;
+	const BORDER: usize = 4;
+
+	#[test]
+	fn png_round_trip() {
+		let text = "otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP&issuer=Example";
+		let code = QrCode::new(text).unwrap();
+		let colors = code.to_colors();
+		let modules = code.width();
+		let size = (modules + 2 * BORDER) * SCALE;
+
+		let mut pixels = vec![255u8; size * size];
+		for (i, color) in colors.iter().enumerate() {
+			if *color == Color::Dark {
+				let (x, y) = ((i % modules + BORDER) * SCALE, (i / modules + BORDER) * SCALE);
+				for dy in 0..SCALE {
+					pixels[(y + dy) * size + x..(y + dy) * size + x + SCALE].fill(0);
+				}
+			}
+		}
+
+		let path = temp_dir("png_round_trip").join("qr.png");
+		let mut encoder = Encoder::new(File::create(&path).unwrap(), size as u32, size as u32);
+		encoder.set_color(ColorType::Grayscale);
+		encoder.set_depth(BitDepth::Eight);
+		encoder.write_header().unwrap().write_image_data(&pixels).unwrap();
+
+		assert_eq!(decode_png(&path).unwrap(), [text]);
+	}
+}
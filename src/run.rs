@@ -1,36 +1,92 @@
-use super::app::{App, CurrentScreen, Data, InputMode};
+use super::app::{App, Confirm, CurrentScreen, Data, InputMode};
+use super::folders::Node;
 use super::ui::ui;
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 use ratatui::{Terminal, prelude::Backend};
-use std::error::Error;
-use tui_input::backend::crossterm::EventHandler;
+use std::{error::Error, time::Duration};
+use tui_input::{InputRequest, backend::crossterm::EventHandler};
+
+// Redraw interval while no key is pressed, keeps TOTP codes and countdowns current
+const TICK: Duration = Duration::from_millis(250);
 
 pub fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Result<bool, Box<dyn Error>> {
 	loop {
 		terminal.draw(|frame| ui(frame, app))?;
+		if !event::poll(TICK)? {
+			continue;
+		}
 
 		let event = event::read()?;
 		if let Event::Key(key) = event {
 			if key.kind == event::KeyEventKind::Release {
 				continue;
 			}
+			app.status = None;
 
 			match app.current_screen {
+				CurrentScreen::Unlock => match key.code {
+					KeyCode::Esc => return Ok(false),
+					KeyCode::Enter => app.unlock(),
+					_ => {
+						app.unlock.input.handle_event(&event);
+					}
+				},
+				CurrentScreen::Migrate => match key.code {
+					KeyCode::Esc => return Ok(false),
+					KeyCode::Enter => {
+						if let Err(err) = app.merge_legacy() {
+							app.unlock.error = Some(err.to_string());
+						}
+					}
+					_ => {}
+				},
 				CurrentScreen::Main => match key.code {
+					KeyCode::Esc if !app.filter.value().is_empty() => app.clear_filter(),
+					KeyCode::Esc if app.node != Node::All => app.select_node(0),
 					KeyCode::Esc => return Ok(true),
-					KeyCode::Char('P') => app.clip_row()?,
-					KeyCode::Char('c') => app.clip_column()?,
-					KeyCode::Char('p') => app.clip_password()?,
+					KeyCode::Char('/') => app.current_screen = CurrentScreen::Search,
+					KeyCode::Enter => app.open_detail(),
+					KeyCode::Char('f') => app.focus_sidebar(),
+					KeyCode::Char('F') => app.toggle_sidebar(),
+					KeyCode::Char('v' | 'V') => app.open_move(),
+					KeyCode::Char('P') => app.clip_row(),
+					KeyCode::Char('c') => app.clip_column(),
+					KeyCode::Char('p') => app.clip_password(),
+					KeyCode::Char('t') => app.clip_totp(),
+					KeyCode::Char('o' | 'O') => app.open_qr(),
+					// Saving re-encrypts the whole vault, not worth it when nothing changed
+					KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+						let changed = app.redo();
+						if changed {
+							app.write()?;
+						}
+					}
+					KeyCode::Char('u' | 'U') => {
+						let changed = app.undo();
+						if changed {
+							app.write()?;
+						}
+					}
+					KeyCode::Char('r') => app.toggle_reveal(),
+					KeyCode::Char('R') => app.toggle_reveal_all(),
+					KeyCode::Char('s') => app.next_sort(),
+					KeyCode::Char('S') => app.reverse_sort(),
 					KeyCode::Char('j') | KeyCode::Down => app.next_row(),
 					KeyCode::Char('k') | KeyCode::Up => app.previous_row(),
 					KeyCode::Char('l') | KeyCode::Right => app.nex_column(),
 					KeyCode::Char('h') | KeyCode::Left => app.previous_column(),
-					KeyCode::Char('d' | 'D') => {
-						app.delete();
-						app.write()?;
-					}
+					KeyCode::Char('d' | 'D') => app.ask(Confirm::Delete),
+					KeyCode::Char('x' | 'X') => app.open_trash(),
 					KeyCode::Char('?') => app.current_screen = CurrentScreen::Help,
-					KeyCode::Char('n' | 'N') => app.current_screen = CurrentScreen::Popup,
+					KeyCode::Char('a' | 'A') => app.open_audit(),
+					KeyCode::Char('b' | 'B') => app.check_breaches(),
+					KeyCode::Char('n' | 'N') => {
+						// New entries start in the folder being looked at
+						if let Node::Folder(path) = &app.node {
+							app.input.folder = path.as_str().into();
+						}
+						app.current_screen = CurrentScreen::Popup;
+					}
 					KeyCode::Char('m' | 'M') => {
 						app.modify();
 						app.current_screen = CurrentScreen::Popup;
@@ -43,6 +99,8 @@ pub fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow:
 							app.input.reset_data();
 							app.is_modify = false;
 						}
+						app.form_error = None;
+						app.show_password = false;
 						app.current_screen = CurrentScreen::Main;
 					}
 					KeyCode::Enter => {
@@ -54,12 +112,33 @@ pub fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow:
 							continue;
 						}
 
-						app.add_password();
+						if let Err(err) = app.add_password() {
+							app.form_error = Some(err.to_string());
+							continue;
+						}
 						app.write()?;
 						app.input.reset_data();
 						app.input_mode = InputMode::default();
+						app.form_error = None;
+						app.show_password = false;
 						app.current_screen = CurrentScreen::Main;
 					}
+					KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => app.generate_password(),
+					KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => app.generate_passphrase(),
+					KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+						app.show_password = !app.show_password
+					}
+					KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+						if app.input_mode == InputMode::Notes {
+							app.input.notes.handle(InputRequest::InsertChar('\n'));
+						}
+					}
+					KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+						if let InputMode::Field(index) = app.input_mode {
+							let field = app.input.field_mut(index);
+							field.secret = !field.secret;
+						}
+					}
 					// Switch fields
 					KeyCode::Down | KeyCode::Tab => app.next_input_mode(),
 					KeyCode::Up => app.prev_input_mode(),
@@ -68,10 +147,114 @@ pub fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow:
 						match app.input_mode {
 							InputMode::Login => app.input.login.handle_event(&event),
 							InputMode::Password => app.input.password.handle_event(&event),
+							InputMode::Confirm => app.input.confirm.handle_event(&event),
 							InputMode::Service => app.input.service.handle_event(&event),
+							InputMode::Totp => app.input.totp.handle_event(&event),
+							InputMode::Url => app.input.url.handle_event(&event),
+							InputMode::Folder => app.input.folder.handle_event(&event),
+							InputMode::Expires => app.input.expires.handle_event(&event),
+							InputMode::Tags => app.input.tags.handle_event(&event),
+							InputMode::Notes => app.input.notes.handle_event(&event),
+							InputMode::Field(index) => app.input.field_mut(index).input.handle_event(&event),
 						};
 					}
 				},
+				CurrentScreen::Audit => match key.code {
+					KeyCode::Esc => app.current_screen = CurrentScreen::Main,
+					KeyCode::Enter => app.jump_to_finding(),
+					KeyCode::Tab => app.toggle_audit_view(),
+					KeyCode::Char('j') | KeyCode::Down => app.next_finding(),
+					KeyCode::Char('k') | KeyCode::Up => app.previous_finding(),
+					_ => {}
+				},
+				CurrentScreen::Search => match key.code {
+					KeyCode::Esc => {
+						app.clear_filter();
+						app.current_screen = CurrentScreen::Main;
+					}
+					// Keep the filter and get the table keys back
+					KeyCode::Enter => app.current_screen = CurrentScreen::Main,
+					KeyCode::Down => app.next_row(),
+					KeyCode::Up => app.previous_row(),
+					_ => {
+						if app.filter.handle_event(&event).is_some() {
+							app.apply_filter();
+						}
+					}
+				},
+				CurrentScreen::Sidebar => match key.code {
+					KeyCode::Esc => {
+						app.select_node(0);
+						app.current_screen = CurrentScreen::Main;
+					}
+					KeyCode::Enter | KeyCode::Tab | KeyCode::Char('f' | 'l') | KeyCode::Right => {
+						app.current_screen = CurrentScreen::Main
+					}
+					KeyCode::Char('j') | KeyCode::Down => app.next_node(),
+					KeyCode::Char('k') | KeyCode::Up => app.previous_node(),
+					_ => {}
+				},
+				CurrentScreen::Move => match key.code {
+					KeyCode::Esc => {
+						app.folder_input.reset();
+						app.current_screen = CurrentScreen::Main;
+					}
+					KeyCode::Enter => {
+						if app.move_selected() {
+							app.write()?;
+						}
+						app.current_screen = CurrentScreen::Main;
+					}
+					_ => {
+						app.folder_input.handle_event(&event);
+					}
+				},
+				CurrentScreen::Detail => match key.code {
+					KeyCode::Esc | KeyCode::Char('q') => app.current_screen = CurrentScreen::Main,
+					KeyCode::Enter | KeyCode::Char('c') => app.clip_detail(),
+					KeyCode::Char('r') => app.toggle_detail_reveal(),
+					KeyCode::Char('o') => {
+						let restored = app.restore_password();
+						if restored {
+							app.write()?;
+						}
+					}
+					KeyCode::Char('j') | KeyCode::Down => app.next_detail(),
+					KeyCode::Char('k') | KeyCode::Up => app.previous_detail(),
+					KeyCode::Char('m' | 'M') => {
+						app.modify();
+						app.current_screen = CurrentScreen::Popup;
+					}
+					_ => {}
+				},
+				CurrentScreen::Confirm => match key.code {
+					KeyCode::Char('y' | 'Y') | KeyCode::Enter => {
+						app.confirmed();
+						app.write()?;
+					}
+					KeyCode::Char('n' | 'N') | KeyCode::Esc => app.cancel_confirm(),
+					_ => {}
+				},
+				CurrentScreen::Trash => match key.code {
+					KeyCode::Esc => app.current_screen = CurrentScreen::Main,
+					KeyCode::Enter | KeyCode::Char('r') => {
+						let restored = app.restore();
+						if restored {
+							app.write()?;
+						}
+					}
+					KeyCode::Char('d') => app.ask(Confirm::Purge),
+					KeyCode::Char('D') => app.ask(Confirm::EmptyTrash),
+					KeyCode::Char('j') | KeyCode::Down => app.next_trash(),
+					KeyCode::Char('k') | KeyCode::Up => app.previous_trash(),
+					_ => {}
+				},
+				CurrentScreen::Qr => {
+					if key.code == KeyCode::Esc {
+						app.qr.clear();
+						app.current_screen = CurrentScreen::Main;
+					}
+				}
 				CurrentScreen::Help => {
 					if key.code == KeyCode::Esc {
 						app.current_screen = CurrentScreen::Main;
@@ -0,0 +1,77 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Fuzzy filter of the table: the characters of the query have to show up in order in the
+// service or login, not necessarily next to each other
+use crate::app::Data;
+
+// Character positions in `text` matching `query`, the tightest match wins so "log" picks
+// "login" over the "l" of "google". Case and whitespace in the query are ignored.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<Vec<usize>> {
+	let query: Vec<char> = query
+		.chars()
+		.filter(|c| !c.is_whitespace())
+		.flat_map(char::to_lowercase)
+		.collect();
+	let text: Vec<char> = text.chars().collect();
+	if query.is_empty() {
+		return Some(Vec::new());
+	}
+
+	let same = |c: char, q: char| c.to_lowercase().eq(q.to_lowercase());
+	let mut best: Option<Vec<usize>> = None;
+	for start in (0..text.len()).filter(|&start| same(text[start], query[0])) {
+		let mut positions = vec![start];
+		let mut next = start + 1;
+		for &q in &query[1..] {
+			match (next..text.len()).find(|&i| same(text[i], q)) {
+				Some(i) => {
+					positions.push(i);
+					next = i + 1;
+				}
+				None => break,
+			}
+		}
+		if positions.len() < query.len() {
+			// Later starts only have less text left
+			break;
+		}
+
+		let span = |positions: &[usize]| positions[positions.len() - 1] - positions[0];
+		if best.as_ref().is_none_or(|best| span(&positions) < span(best)) {
+			best = Some(positions);
+		}
+	}
+	best
+}
+
+// Matched positions in the service and in the login of an entry. Both are searched as one
+// text, so a query can start in the service and go on in the login.
+pub fn entry_match<T: Data>(query: &str, item: &T) -> Option<[Vec<usize>; 2]> {
+	let service_len = item.service().chars().count();
+	let positions = fuzzy_match(query, &format!("{} {}", item.service(), item.login()))?;
+	let (service, login): (Vec<usize>, Vec<usize>) = positions.into_iter().partition(|&i| i < service_len);
+
+	Some([service, login.into_iter().map(|i| i - service_len - 1).collect()])
+}
+
+#[cfg(test)]
+mod test_search {
+	use super::{entry_match, fuzzy_match};
+	use crate::app::Password;
+
+	#[test]
+	fn subsequence() {
+		assert_eq!(fuzzy_match("gh", "GitHub"), Some(vec![0, 3]));
+		assert_eq!(fuzzy_match("hg", "GitHub"), None);
+		assert_eq!(fuzzy_match("log", "google login"), Some(vec![7, 8, 9]));
+		assert_eq!(fuzzy_match("", "anything"), Some(vec![]));
+		assert_eq!(fuzzy_match("x", ""), None);
+	}
+
+	#[test]
+	fn service_and_login() {
+		let item = Password::new("gitlab", "me@work", "secret");
+		assert_eq!(entry_match("lab me", &item), Some([vec![3, 4, 5], vec![0, 1]]));
+		assert_eq!(entry_match("work", &item), Some([vec![], vec![3, 4, 5, 6]]));
+		assert_eq!(entry_match("secret", &item), None);
+	}
+}
@@ -0,0 +1,301 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Password strength estimation in the spirit of zxcvbn: the password is covered by the
+// cheapest combination of known patterns and brute-forced characters, and the total bits
+// of that cover are the estimate.
+use crate::generator::EFF_WORDLIST;
+use std::{collections::HashMap, sync::LazyLock};
+
+// Most common leaked passwords, most popular first
+const COMMON_PASSWORDS: &str = include_str!("../assets/common_passwords.txt");
+const KEYBOARD_ROWS: &[&str] = &["`1234567890-=", "qwertyuiop[]\\", "asdfghjkl;'", "zxcvbnm,./"];
+const MIN_WORD_LEN: usize = 4;
+
+static COMMON: LazyLock<HashMap<&'static str, usize>> = LazyLock::new(|| {
+	COMMON_PASSWORDS
+		.lines()
+		.enumerate()
+		.map(|(rank, password)| (password, rank + 1))
+		.collect()
+});
+
+static WORDS: LazyLock<HashMap<&'static str, usize>> = LazyLock::new(|| {
+	EFF_WORDLIST
+		.lines()
+		.filter_map(|line| line.split_whitespace().last())
+		.filter(|word| word.len() >= MIN_WORD_LEN)
+		.enumerate()
+		.map(|(rank, word)| (word, rank + 1))
+		.collect()
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Score {
+	VeryWeak,
+	Weak,
+	Fair,
+	Strong,
+	VeryStrong,
+}
+
+impl Score {
+	pub fn label(self) -> &'static str {
+		match self {
+			Score::VeryWeak => "very weak",
+			Score::Weak => "weak",
+			Score::Fair => "fair",
+			Score::Strong => "strong",
+			Score::VeryStrong => "very strong",
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pattern {
+	Common,
+	Dictionary,
+	Sequence,
+	Keyboard,
+	Repeat,
+	Year,
+}
+
+impl Pattern {
+	pub fn warning(self) -> &'static str {
+		match self {
+			Pattern::Common => "one of the most common passwords",
+			Pattern::Dictionary => "contains a dictionary word",
+			Pattern::Sequence => "contains a sequence like abc or 123",
+			Pattern::Keyboard => "contains a keyboard pattern like qwerty",
+			Pattern::Repeat => "contains repeated characters",
+			Pattern::Year => "contains a year",
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Strength {
+	pub score: Score,
+	pub bits: f64,
+	// Pattern that covers most of the password, if any
+	pub pattern: Option<Pattern>,
+}
+
+impl Strength {
+	pub fn warning(&self) -> &'static str {
+		match self.pattern {
+			Some(pattern) => pattern.warning(),
+			None if self.score < Score::Fair => "too short",
+			None => "",
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Match {
+	start: usize,
+	end: usize,
+	bits: f64,
+	pattern: Pattern,
+}
+
+pub fn estimate(password: &str) -> Strength {
+	let chars: Vec<char> = password.chars().collect();
+	let matches = find_matches(&chars);
+
+	// best[i]: cheapest cover of chars[..i] and the match that ends it (None for a brute-forced char)
+	let mut best: Vec<(f64, Option<Match>)> = vec![(0.0, None); chars.len() + 1];
+	for end in 1..=chars.len() {
+		best[end] = (best[end - 1].0 + char_bits(chars[end - 1]), None);
+		for m in matches.iter().filter(|m| m.end == end) {
+			let bits = best[m.start].0 + m.bits;
+			if bits < best[end].0 {
+				best[end] = (bits, Some(*m));
+			}
+		}
+	}
+
+	// Walk the cover back and pick the pattern that spans most characters
+	let mut end = chars.len();
+	let mut pattern: Option<(usize, Pattern)> = None;
+	while end > 0 {
+		match best[end].1 {
+			Some(m) => {
+				if pattern.is_none_or(|(len, _)| m.end - m.start > len) {
+					pattern = Some((m.end - m.start, m.pattern));
+				}
+				end = m.start;
+			}
+			None => end -= 1,
+		}
+	}
+
+	let bits = best[chars.len()].0;
+	let score = match bits {
+		bits if bits < 25.0 => Score::VeryWeak,
+		bits if bits < 40.0 => Score::Weak,
+		bits if bits < 60.0 => Score::Fair,
+		bits if bits < 80.0 => Score::Strong,
+		_ => Score::VeryStrong,
+	};
+
+	Strength {
+		score,
+		bits,
+		pattern: pattern.map(|(_, pattern)| pattern),
+	}
+}
+
+// Cost of guessing a single character by brute force
+fn char_bits(c: char) -> f64 {
+	let pool: f64 = match c {
+		'a'..='z' | 'A'..='Z' => 26.0,
+		'0'..='9' => 10.0,
+		c if c.is_ascii_punctuation() || c == ' ' => 33.0,
+		_ => 100.0,
+	};
+	pool.log2()
+}
+
+fn find_matches(chars: &[char]) -> Vec<Match> {
+	let mut matches = Vec::new();
+	let lower: Vec<char> = chars.iter().flat_map(|c| c.to_lowercase()).collect();
+	if lower.len() != chars.len() {
+		return matches;
+	}
+	let unleet: Vec<char> = lower.iter().map(|c| unleet(*c)).collect();
+
+	for start in 0..chars.len() {
+		for end in start + 3..=chars.len() {
+			let span = end - start;
+			let len_bits = (span as f64).log2();
+
+			// Common passwords and dictionary words, capitals and leetspeak cost a few bits more
+			if let Some((rank, pattern, extra)) =
+				lookup_word(&chars[start..end], &lower[start..end], &unleet[start..end])
+			{
+				matches.push(Match {
+					start,
+					end,
+					bits: (rank as f64).log2() + 1.0 + extra,
+					pattern,
+				});
+			}
+
+			let run = &lower[start..end];
+			if run.iter().all(|c| *c == run[0]) {
+				matches.push(Match {
+					start,
+					end,
+					bits: char_bits(chars[start]) + len_bits,
+					pattern: Pattern::Repeat,
+				});
+			}
+			if is_sequence(run) {
+				matches.push(Match {
+					start,
+					end,
+					bits: 26f64.log2() + len_bits + 1.0,
+					pattern: Pattern::Sequence,
+				});
+			}
+			if KEYBOARD_ROWS.iter().any(|row| is_keyboard_run(row, run)) {
+				matches.push(Match {
+					start,
+					end,
+					bits: 47f64.log2() + len_bits + 1.0,
+					pattern: Pattern::Keyboard,
+				});
+			}
+			if span == 4 && is_year(run) {
+				matches.push(Match {
+					start,
+					end,
+					bits: 200f64.log2(),
+					pattern: Pattern::Year,
+				});
+			}
+		}
+	}
+
+	matches
+}
+
+fn lookup_word(original: &[char], lower: &[char], unleet: &[char]) -> Option<(usize, Pattern, f64)> {
+	let upper = original.iter().zip(lower).filter(|(a, b)| a != b).count();
+	let capital_bits = match upper {
+		0 => 0.0,
+		1 if original[0].is_uppercase() => 1.0,
+		n => n as f64 + 1.0,
+	};
+	let leet_bits = lower.iter().zip(unleet).filter(|(a, b)| a != b).count() as f64;
+
+	let find = |word: &[char], extra: f64| {
+		let word: String = word.iter().collect();
+		if let Some(rank) = COMMON.get(word.as_str()) {
+			return Some((*rank, Pattern::Common, extra));
+		}
+		if word.chars().count() >= MIN_WORD_LEN {
+			return WORDS.get(word.as_str()).map(|rank| (*rank, Pattern::Dictionary, extra));
+		}
+		None
+	};
+
+	find(lower, capital_bits).or_else(|| find(unleet, capital_bits + leet_bits))
+}
+
+fn unleet(c: char) -> char {
+	match c {
+		'4' | '@' => 'a',
+		'3' => 'e',
+		'1' | '!' => 'i',
+		'0' => 'o',
+		'5' | '$' => 's',
+		'7' => 't',
+		c => c,
+	}
+}
+
+fn is_sequence(run: &[char]) -> bool {
+	let step = run[1] as i64 - run[0] as i64;
+	step.abs() == 1 && run.windows(2).all(|pair| pair[1] as i64 - pair[0] as i64 == step)
+}
+
+fn is_keyboard_run(row: &str, run: &[char]) -> bool {
+	let run: String = run.iter().collect();
+	let reversed: String = run.chars().rev().collect();
+	row.contains(&run) || row.contains(&reversed)
+}
+
+fn is_year(run: &[char]) -> bool {
+	let year: String = run.iter().collect();
+	matches!(year.parse::<u32>(), Ok(1900..=2099))
+}
+
+#[cfg(test)]
+mod test_strength {
+	use super::{Pattern, Score, estimate};
+
+	#[test]
+	fn common_passwords() {
+		for password in ["password", "P@ssw0rd", "123456", "qwerty"] {
+			let strength = estimate(password);
+			assert_eq!(strength.score, Score::VeryWeak, "{password}: {strength:?}");
+			assert_eq!(strength.pattern, Some(Pattern::Common), "{password}");
+		}
+	}
+
+	#[test]
+	fn patterns() {
+		assert_eq!(estimate("abcdefgh").pattern, Some(Pattern::Sequence));
+		assert_eq!(estimate("zzzzzzzz").pattern, Some(Pattern::Repeat));
+		assert_eq!(estimate("asdfghjkl").pattern, Some(Pattern::Keyboard));
+		assert_eq!(estimate("Bulldozer1987").pattern, Some(Pattern::Dictionary));
+		assert!(estimate("Bulldozer1987").score <= Score::Weak);
+	}
+
+	#[test]
+	fn random_is_strong() {
+		assert!(estimate("x7#Kq9!mT2$vLp4@wZ8&").score >= Score::Strong);
+		assert!(estimate("correct-horse-battery-staple-glove").score >= Score::Fair);
+	}
+}
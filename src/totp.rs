@@ -0,0 +1,185 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Time-based one-time passwords, RFC 6238
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac, digest::KeyInit};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_DIGITS: u32 = 6;
+pub const DEFAULT_PERIOD: u64 = 30;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Algorithm {
+	#[default]
+	Sha1,
+	Sha256,
+	Sha512,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Totp {
+	// Stored as base32 like authenticator apps show it
+	#[serde(with = "base32")]
+	secret: Vec<u8>,
+	pub algorithm: Algorithm,
+	pub digits: u32,
+	// Seconds every code is valid for
+	pub period: u64,
+}
+
+impl Algorithm {
+	// Name used in otpauth:// URIs
+	pub fn name(self) -> &'static str {
+		match self {
+			Algorithm::Sha1 => "SHA1",
+			Algorithm::Sha256 => "SHA256",
+			Algorithm::Sha512 => "SHA512",
+		}
+	}
+}
+
+impl Totp {
+	pub fn new(secret: &str, algorithm: Algorithm, digits: u32, period: u64) -> Result<Self> {
+		Self::from_bytes(decode_secret(secret)?, algorithm, digits, period)
+	}
+
+	// Secret that is already decoded, like in authenticator exports
+	pub fn from_bytes(secret: Vec<u8>, algorithm: Algorithm, digits: u32, period: u64) -> Result<Self> {
+		if secret.is_empty() {
+			bail!("TOTP secret can't be empty.");
+		}
+		if !(6..=8).contains(&digits) {
+			bail!("TOTP codes have 6 to 8 digits, not {digits}.");
+		}
+		if period == 0 {
+			bail!("TOTP period can't be 0 seconds.");
+		}
+
+		Ok(Self {
+			secret,
+			algorithm,
+			digits,
+			period,
+		})
+	}
+
+	// SHA-1, 6 digits and 30 seconds, what almost every service uses
+	pub fn from_secret(secret: &str) -> Result<Self> {
+		Self::new(secret, Algorithm::default(), DEFAULT_DIGITS, DEFAULT_PERIOD)
+	}
+
+	pub fn secret(&self) -> String {
+		BASE32_NOPAD.encode(&self.secret)
+	}
+
+	// Current code and the seconds it stays valid
+	pub fn now(&self) -> (String, u64) {
+		let time = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |time| time.as_secs());
+		(self.code_at(time), self.period - time % self.period)
+	}
+
+	pub fn code_at(&self, time: u64) -> String {
+		let counter = (time / self.period).to_be_bytes();
+		let digest = match self.algorithm {
+			Algorithm::Sha1 => sign::<Hmac<Sha1>>(&self.secret, &counter),
+			Algorithm::Sha256 => sign::<Hmac<Sha256>>(&self.secret, &counter),
+			Algorithm::Sha512 => sign::<Hmac<Sha512>>(&self.secret, &counter),
+		};
+
+		// Dynamic truncation, RFC 4226 section 5.3
+		let offset = (digest[digest.len() - 1] & 0xf) as usize;
+		let binary = u32::from_be_bytes(digest[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+		format!(
+			"{:0width$}",
+			binary % 10u32.pow(self.digits),
+			width = self.digits as usize
+		)
+	}
+}
+
+fn sign<M: Mac + KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+	let mut mac = <M as KeyInit>::new_from_slice(key).expect("HMAC accepts keys of any length");
+	mac.update(message);
+	mac.finalize().into_bytes().to_vec()
+}
+
+// Authenticator apps show secrets lowercase, in groups or padded, all of that is accepted
+fn decode_secret(secret: &str) -> Result<Vec<u8>> {
+	let secret: String = secret
+		.chars()
+		.filter(|c| !c.is_whitespace() && *c != '-' && *c != '=')
+		.map(|c| c.to_ascii_uppercase())
+		.collect();
+
+	match BASE32_NOPAD.decode(secret.as_bytes()) {
+		Ok(secret) if !secret.is_empty() => Ok(secret),
+		_ => bail!("TOTP secret isn't valid base32."),
+	}
+}
+
+mod base32 {
+	use data_encoding::BASE32_NOPAD;
+	use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+	pub fn serialize<S: Serializer>(secret: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&BASE32_NOPAD.encode(secret))
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+		super::decode_secret(&String::deserialize(deserializer)?).map_err(Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod test_totp {
+	use super::{Algorithm, Totp};
+	use data_encoding::BASE32_NOPAD;
+
+	// Test vectors from RFC 6238 appendix B
+	#[test]
+	fn rfc_vectors() {
+		let cases = [
+			(
+				Algorithm::Sha1,
+				"12345678901234567890",
+				["94287082", "07081804", "69279037"],
+			),
+			(
+				Algorithm::Sha256,
+				"12345678901234567890123456789012",
+				["46119246", "68084774", "90698825"],
+			),
+			(
+				Algorithm::Sha512,
+				"1234567890123456789012345678901234567890123456789012345678901234",
+				["90693936", "25091201", "38618901"],
+			),
+		];
+
+		for (algorithm, key, codes) in cases {
+			let totp = Totp::new(&BASE32_NOPAD.encode(key.as_bytes()), algorithm, 8, 30).unwrap();
+			for (time, code) in [59, 1111111109, 2000000000].into_iter().zip(codes) {
+				assert_eq!(totp.code_at(time), code, "{algorithm:?} at {time}");
+			}
+		}
+	}
+
+	#[test]
+	fn secret_formats() {
+		let totp = Totp::from_secret("jbsw y3dp-ehpk 3pxp").unwrap();
+		assert_eq!(totp.secret(), "JBSWY3DPEHPK3PXP");
+		assert_eq!(totp.code_at(59).len(), 6);
+
+		assert!(Totp::from_secret("not base32!").is_err());
+		assert!(Totp::from_secret("").is_err());
+		assert!(Totp::new("JBSWY3DPEHPK3PXP", Algorithm::Sha1, 9, 30).is_err());
+	}
+}
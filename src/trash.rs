@@ -0,0 +1,54 @@
+// #![warn(clippy::all, clippy::pedantic)]
+// Deleted entries wait in the trash of the vault until they are restored, purged by hand or
+// left there longer than the retention period
+use crate::app::Password;
+use crate::date::DAY;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Trashed {
+	pub item: Password,
+	// Unix time it was deleted at
+	pub deleted: u64,
+}
+
+impl Trashed {
+	// When it goes for good, None if the trash is kept forever
+	pub fn purge_at(&self, retention_days: u64) -> Option<u64> {
+		(retention_days > 0).then(|| self.deleted + retention_days * DAY)
+	}
+}
+
+// Drop entries past the retention period, returns the ids of the ones that went
+pub fn purge_expired(trash: &mut Vec<Trashed>, retention_days: u64, now: u64) -> Vec<Uuid> {
+	let (expired, kept) = std::mem::take(trash)
+		.into_iter()
+		.partition(|trashed| trashed.purge_at(retention_days).is_some_and(|at| at <= now));
+	*trash = kept;
+	expired.into_iter().map(|trashed: Trashed| trashed.item.id).collect()
+}
+
+#[cfg(test)]
+mod test_trash {
+	use super::{Trashed, purge_expired};
+	use crate::app::Password;
+	use crate::date::DAY;
+
+	#[test]
+	fn retention() {
+		let mut trash: Vec<Trashed> = [10 * DAY, 40 * DAY]
+			.into_iter()
+			.map(|deleted| Trashed {
+				item: Password::new("a", "b", "c"),
+				deleted,
+			})
+			.collect();
+
+		let first = trash[0].item.id;
+		assert!(purge_expired(&mut trash, 0, 1000 * DAY).is_empty());
+		assert_eq!(purge_expired(&mut trash, 30, 45 * DAY), [first]);
+		assert_eq!(trash[0].deleted, 40 * DAY);
+	}
+}
@@ -1,34 +1,78 @@
 // #![allow(unused)]
 // #![warn(clippy::all, clippy::pedantic)]
 
-use super::app::{App, CurrentScreen, Data, ITEM_HEIGHT, InputMode};
+use super::app::{App, AuditView, Confirm, CurrentScreen, Data, ExpiryState, ITEM_HEIGHT, InputMode, Password};
+use super::audit::{Finding, Issue};
+use super::conf::SortKey;
+use super::date;
+use super::folders::Node;
+use super::legacy::LEGACY_FILE;
+use super::strength::Score;
+use super::vault::VAULT_FILE;
 use ratatui::{
 	Frame,
 	layout::{Constraint, Direction, Layout, Margin, Rect},
 	style::{Color, Modifier, Style, Stylize, palette::tailwind},
-	text::Text,
+	text::{Line, Span, Text},
 	widgets::{
-		Block, BorderType, Borders, Cell, Clear, HighlightSpacing, Paragraph, Row, Scrollbar, ScrollbarOrientation,
-		Table,
+		Block, BorderType, Borders, Cell, Clear, HighlightSpacing, List, ListItem, Paragraph, Row, Scrollbar,
+		ScrollbarOrientation, Table, Wrap,
 	},
 };
+use tui_input::Input;
 use unicode_width::UnicodeWidthStr;
 
 const BAR_SYMBOL: &str = " █ ";
 const SCROLLBAR_BEGIN_SYMBOL: &str = "▲";
 const SCROLLBAR_END_SYMBOL: &str = "▼";
-const INFO_TEXT: [&str; 1] =
-	["(Esc) quit | (n) new password | (Enter) send password | (d) delete password | (m) modify password | (?) help"];
-const HELP_TEXT: [&str; 15] = [
+const MASK_SYMBOL: &str = "•";
+// Masked passwords all look the same, so their length doesn't show either
+const MASK_WIDTH: usize = 8;
+const BREACH_SYMBOL: &str = "⚠ ";
+const EXPIRED_SYMBOL: &str = "⌛ ";
+// Line breaks of notes in the single line form field
+const NEWLINE_SYMBOL: &str = "⏎";
+// "1234 5678  30s"
+const TOTP_WIDTH: u16 = 14;
+// Longer tag lists are cut off in the table
+const TAGS_MAX_WIDTH: u16 = 24;
+const SIDEBAR_WIDTH: u16 = 26;
+const INFO_TEXT: [&str; 1] = [
+	"(Esc) quit | (n) new password | (Enter) details | (d) delete password | (x) trash | (m) modify password | (/) search | (?) help",
+];
+const HELP_TEXT: [&str; 38] = [
 	"--- Global --- ",
 	"(Esc) - quit",
 	"(n) - new password",
-	"(d) - delete password",
+	"(d) - move password to the trash",
+	"(x) - trash: restore or purge deleted passwords",
+	"(u) - undo the last change",
+	"(Ctrl-r) - redo",
 	"(m) - modify password",
-	"(Enter) - send password",
+	"(Enter) - show every field of the entry",
 	"(c) - copy column",
 	"(p) - copy password",
 	"(P) - copy row",
+	"(t) - copy TOTP code",
+	"(o) - show TOTP as QR code",
+	"(r) - reveal selected password",
+	"(R) - reveal all passwords",
+	"(a) - audit weak and reused passwords",
+	"(b) - check passwords against breach data",
+	"(/) - search service and login",
+	"(s) - sort by the next key",
+	"(S) - reverse the sort",
+	"(f) - browse folders and tags",
+	"(F) - show or hide the sidebar",
+	"(v) - move to a folder",
+	" ",
+	"--- Form ---",
+	"(Tab, ↓, ↑) - switch field",
+	"(Ctrl-g) - generate password",
+	"(Ctrl-p) - generate passphrase",
+	"(Ctrl-r) - show or hide password",
+	"(Ctrl-n) - new line in notes",
+	"(Ctrl-s) - make a custom field secret",
 	" ",
 	"--- Navigation ---",
 	"(↑, k) - move up ",
@@ -69,23 +113,46 @@ impl TableColors {
 }
 
 pub fn ui(frame: &mut Frame, app: &mut App) {
-	let vertical = Layout::vertical([Constraint::Max(3), Constraint::Min(5), Constraint::Max(3)]);
-	let rects = vertical.split(frame.area());
+	match app.current_screen {
+		CurrentScreen::Unlock => return render_unlock(app, frame),
+		CurrentScreen::Migrate => return render_migrate(app, frame),
+		_ => {}
+	}
 
+	let [body, footer] = Layout::vertical([Constraint::Min(8), Constraint::Max(3)]).areas(frame.area());
+	let sidebar_width = if app.show_sidebar && app.has_sidebar() {
+		SIDEBAR_WIDTH
+	} else {
+		0
+	};
+	let [sidebar, main] = Layout::horizontal([Constraint::Length(sidebar_width), Constraint::Fill(1)]).areas(body);
+	let rects = Layout::vertical([Constraint::Max(3), Constraint::Min(5)]).split(main);
+
+	render_sidebar(app, frame, sidebar);
 	render_header(app, frame, rects[0]);
 	render_table(app, frame, rects[1]);
 	render_scrollbar(app, frame, rects[1]);
-	render_footer(app, frame, rects[2]);
+	render_footer(app, frame, footer);
 
 	match app.current_screen {
 		CurrentScreen::Popup => render_popup(app, frame),
 		CurrentScreen::Help => render_help(frame),
+		CurrentScreen::Audit => render_audit(app, frame),
+		CurrentScreen::Qr => render_qr(app, frame),
+		CurrentScreen::Detail => render_detail(app, frame),
+		CurrentScreen::Trash => render_trash(app, frame),
+		CurrentScreen::Confirm => {
+			if app.confirm != Some(Confirm::Delete) {
+				render_trash(app, frame);
+			}
+			render_confirm(app, frame);
+		}
 		_ => {}
 	}
 }
 
 fn render_help(frame: &mut Frame) {
-	let area = centered_rect(40, 40, frame.area());
+	let area = centered_rect(40, 60, frame.area());
 	let block = Block::default().title("Help").bg(Color::Reset).borders(Borders::ALL);
 	let paragraph = Paragraph::new(Text::from_iter(HELP_TEXT))
 		.block(block.clone())
@@ -115,24 +182,108 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 		.split(popup_layout[1])[1] // Return the middle chunk
 }
 
-fn render_header(app: &App, frame: &mut Frame, area: Rect) {
+// Every entry, the folder tree and the tags with their counts, the picked one narrows the table
+fn render_sidebar(app: &mut App, frame: &mut Frame, area: Rect) {
+	if area.width == 0 {
+		return;
+	}
+	let focused = app.current_screen == CurrentScreen::Sidebar;
 	let block = Block::default()
+		.borders(Borders::RIGHT)
+		.title(match focused {
+			true => " (Enter) table ",
+			false => " (f) folders ",
+		})
+		.bg(app.colors.buffer_bg);
+	let width = block.inner(area).width as usize;
+	let items = app.sidebar.iter().map(|entry| {
+		let label = format!("{}{}", "  ".repeat(entry.depth), entry.label());
+		let count = entry.count.to_string();
+		let gap = width.saturating_sub(label.width() + count.width() + 3).max(1);
+		let line = Line::from(vec![
+			Span::raw(label),
+			Span::raw(" ".repeat(gap)),
+			Span::raw(count).dim(),
+		]);
+		match entry.node {
+			Node::Tag(_) => ListItem::new(line).fg(tailwind::SKY.c300),
+			_ => ListItem::new(line),
+		}
+	});
+	let highlight = match focused {
+		true => Style::new()
+			.add_modifier(Modifier::REVERSED)
+			.fg(app.colors.selected_row_style_fg),
+		false => Style::new().fg(app.colors.selected_row_style_fg).bold(),
+	};
+	let list = List::new(items)
+		.block(block)
+		.highlight_style(highlight)
+		.highlight_symbol("▌");
+
+	frame.render_stateful_widget(list, area, &mut app.sidebar_state);
+}
+
+fn render_header(app: &App, frame: &mut Frame, area: Rect) {
+	let sort = app.config.table.sort;
+	let arrow = if app.config.table.sort_descending { "▼" } else { "▲" };
+	let mut block = Block::default()
 		.borders(Borders::TOP | Borders::BOTTOM)
+		.title(Line::from(format!(" (s) sort: {} {arrow} ", sort.name())).right_aligned())
 		.bg(app.colors.header_bg);
+	// The sidebar may be hidden, the table still says what it's narrowed to
+	match &app.node {
+		Node::All => {}
+		Node::Tag(tag) => block = block.title(format!(" #{tag} ")),
+		node => block = block.title(format!(" {} ", node.name())),
+	}
 	let header_style = Style::default().fg(app.colors.header_fg);
+	// Columns that are the sort key get the arrow too
+	let label = |name: &str, key: SortKey| match key == sort {
+		true => format!("  {name} {arrow}"),
+		false => format!("  {name}"),
+	};
+	let totp_width = if app.has_totp() { TOTP_WIDTH } else { 0 };
 	let chunks = Layout::default()
 		.direction(Direction::Horizontal)
 		.constraints([
-			Constraint::Ratio(1, 3),
-			Constraint::Ratio(1, 3),
-			Constraint::Ratio(1, 3),
+			Constraint::Fill(1),
+			Constraint::Fill(1),
+			Constraint::Fill(1),
+			Constraint::Length(totp_width),
+			Constraint::Length(tags_width(app)),
 		])
 		.split(block.inner(area));
 
 	frame.render_widget(block, area);
-	frame.render_widget(Paragraph::new("  Service").style(header_style), chunks[0]);
-	frame.render_widget(Paragraph::new("  Login").style(header_style), chunks[1]);
-	frame.render_widget(Paragraph::new("  Password").style(header_style), chunks[2]);
+	frame.render_widget(
+		Paragraph::new(label("Service", SortKey::Service)).style(header_style),
+		chunks[0],
+	);
+	frame.render_widget(
+		Paragraph::new(label("Login", SortKey::Login)).style(header_style),
+		chunks[1],
+	);
+	frame.render_widget(
+		Paragraph::new(label("Password", SortKey::Strength)).style(header_style),
+		chunks[2],
+	);
+	frame.render_widget(Paragraph::new("TOTP").style(header_style), chunks[3]);
+	frame.render_widget(Paragraph::new(" Tags").style(header_style), chunks[4]);
+}
+
+// Width of the tags column, 0 hides it while no entry has tags
+fn tags_width(app: &App) -> u16 {
+	let longest = app
+		.items
+		.iter()
+		.map(|item| item.tags.join(", ").width())
+		.max()
+		.unwrap_or(0) as u16;
+	match longest {
+		0 => 0,
+		_ => (longest + 1).min(TAGS_MAX_WIDTH),
+	}
 }
 
 fn render_table(app: &mut App, frame: &mut Frame, area: Rect) {
@@ -144,27 +295,84 @@ fn render_table(app: &mut App, frame: &mut Frame, area: Rect) {
 		.add_modifier(Modifier::REVERSED)
 		.fg(app.colors.selected_cell_style_fg);
 
-	let rows = app.items.iter().enumerate().map(|(i, data)| {
-		let color = match i % 2 {
-			0 => app.colors.normal_row_color,
-			_ => app.colors.alt_row_color,
-		};
-		data.ref_array()
-			.into_iter()
-			.skip(1)
-			.map(|content| Cell::from(Text::from(format!("\n{content}\n"))))
-			.collect::<Row>()
-			.style(Style::new().fg(app.colors.row_fg).bg(color))
-			.height(ITEM_HEIGHT as u16)
-	});
-
-	let longest_item_lens = constraint_len_calculator(&app.items);
+	let has_totp = app.has_totp();
+	let tags_width = tags_width(app);
+	let now = date::now();
+	let rows = app
+		.view
+		.iter()
+		.zip(&app.view_rows)
+		.enumerate()
+		.map(|(i, (&index, row))| {
+			let data = &app.items[index];
+			let color = match i % 2 {
+				0 => app.colors.normal_row_color,
+				_ => app.colors.alt_row_color,
+			};
+			let strength = strength_color(row.strength.score);
+			let breached = app.is_breached(data);
+			let expiry = data.expiry(&app.config.expiry, now);
+			let expires_at = data.expires_at(app.config.expiry.max_age_days);
+			let revealed = app.is_revealed(index);
+			let mut cells: Vec<Cell> = data
+				.ref_array()
+				.into_iter()
+				.skip(1)
+				.enumerate()
+				.map(|(column, content)| {
+					let mut line = match column {
+						0 | 1 => highlight(&content, &row.matched[column]),
+						2 if !revealed => Line::from(MASK_SYMBOL.repeat(MASK_WIDTH)),
+						_ => Line::from(content),
+					};
+					if column == 0 && expiry == Some(ExpiryState::Expired) {
+						line.spans.insert(0, Span::raw(EXPIRED_SYMBOL));
+					}
+					if column == 0 && breached {
+						line.spans.insert(0, Span::raw(BREACH_SYMBOL));
+					}
+					// The URL goes under the service and the expiry under the login in the row's
+					// last line
+					let below = match (column, expires_at) {
+						(0, _) => Line::from(data.url.clone()).dim(),
+						(1, _) if data.age_unknown(app.config.expiry.max_age_days) => Line::from("age unknown").dim(),
+						(1, Some(at)) if at <= now => Line::from(format!("expired {}", date::format(at))).dim(),
+						(1, Some(at)) => Line::from(format!("expires {}", date::format(at))).dim(),
+						_ => Line::default(),
+					};
+					let cell = Cell::from(Text::from(vec![Line::default(), line, below]));
+					match column {
+						2 if !breached => cell.fg(strength),
+						_ => cell,
+					}
+				})
+				.collect();
+			cells.push(match has_totp {
+				true => totp_cell(data),
+				false => Cell::default(),
+			});
+			if tags_width > 0 {
+				cells.push(Cell::from(Text::from(format!("\n {}\n", data.tags.join(", ")))));
+			}
+			Row::new(cells)
+				.style(match (breached, expiry) {
+					(true, _) => Style::new().fg(tailwind::RED.c500).bg(color).bold(),
+					(false, Some(ExpiryState::Expired)) => Style::new().fg(tailwind::ORANGE.c500).bg(color).bold(),
+					(false, Some(ExpiryState::Soon)) => Style::new().fg(tailwind::YELLOW.c400).bg(color),
+					(false, None) => Style::new().fg(app.colors.row_fg).bg(color),
+				})
+				.height(ITEM_HEIGHT as u16)
+		});
+
+	let longest_item_lens = constraint_len_calculator(&app.items, |index| app.is_revealed(index));
 	let table = Table::new(
 		rows,
 		[
 			Constraint::Min(longest_item_lens.0 + 1),
 			Constraint::Min(longest_item_lens.1 + 1),
 			Constraint::Min(longest_item_lens.2),
+			Constraint::Length(if has_totp { TOTP_WIDTH } else { 0 }),
+			Constraint::Length(tags_width),
 		],
 	)
 	.row_highlight_style(selected_row_style)
@@ -177,6 +385,314 @@ fn render_table(app: &mut App, frame: &mut Frame, area: Rect) {
 	frame.render_stateful_widget(table, area, &mut app.state);
 }
 
+// Characters matched by the search filter stand out
+fn highlight(text: &str, positions: &[usize]) -> Line<'static> {
+	let style = Style::new()
+		.fg(tailwind::AMBER.c400)
+		.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+	text.chars()
+		.enumerate()
+		.map(|(i, c)| match positions.contains(&i) {
+			true => Span::styled(c.to_string(), style),
+			false => Span::raw(c.to_string()),
+		})
+		.collect()
+}
+
+// Current code split in half for reading, turns red in the last seconds
+fn totp_cell(data: &Password) -> Cell<'static> {
+	let Some(totp) = &data.totp else {
+		return Cell::default();
+	};
+
+	let (code, left) = totp.now();
+	let (first, second) = code.split_at(code.len() / 2);
+	let cell = Cell::from(Text::from(format!("\n{first} {second} {left:>3}s\n")));
+	match left {
+		0..=5 => cell.fg(tailwind::RED.c500),
+		_ => cell,
+	}
+}
+
+// Every attribute of the selected entry, one per line, secrets masked until (r)
+fn render_detail(app: &mut App, frame: &mut Frame) {
+	let area = centered_rect(70, 80, frame.area());
+	let details = app.details();
+	let title = match details.first() {
+		Some(service) => format!("Details: {}", service.value),
+		None => "Details".into(),
+	};
+	let block = Block::default()
+		.title(title)
+		.title(
+			Line::from("(c, Enter) copy | (r) reveal | (o) restore old password | (m) modify | (Esc) back")
+				.right_aligned(),
+		)
+		.borders(Borders::ALL)
+		.bg(Color::Reset);
+
+	let label_width = details.iter().map(|detail| detail.label.width()).max().unwrap_or(0) as u16;
+	let rows = details.iter().enumerate().map(|(line, detail)| {
+		let value = match detail.secret && !app.is_detail_revealed(line) {
+			true => Text::from(MASK_SYMBOL.repeat(MASK_WIDTH)),
+			false => Text::from(detail.value.as_str()),
+		};
+		let height = value.height().max(1) as u16;
+		Row::new([
+			Cell::from(detail.label.as_str()).fg(app.colors.header_fg),
+			Cell::from(value),
+		])
+		.height(height)
+	});
+	let table = Table::new(rows, [Constraint::Length(label_width + 1), Constraint::Fill(1)])
+		.row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+		.block(block);
+
+	frame.render_widget(Clear, area);
+	frame.render_stateful_widget(table, area, &mut app.detail_state);
+}
+
+fn strength_color(score: Score) -> Color {
+	match score {
+		Score::VeryWeak => tailwind::RED.c500,
+		Score::Weak => tailwind::ORANGE.c500,
+		Score::Fair => tailwind::YELLOW.c400,
+		Score::Strong => tailwind::LIME.c400,
+		Score::VeryStrong => tailwind::GREEN.c500,
+	}
+}
+
+fn render_audit(app: &mut App, frame: &mut Frame) {
+	let area = centered_rect(80, 70, frame.area());
+	let title = match app.audit_view {
+		AuditView::Findings => "Audit: all findings",
+		AuditView::Reused => "Audit: reused passwords",
+	};
+	let block = Block::default()
+		.title(title)
+		.title(Line::from("(Tab) switch view | (Enter) go to entry | (Esc) back").right_aligned())
+		.borders(Borders::ALL)
+		.bg(Color::Reset);
+
+	frame.render_widget(Clear, area);
+	if app.audit_rows().is_empty() {
+		let text = match app.audit_view {
+			AuditView::Findings => "No weak, reused or breached passwords found.",
+			AuditView::Reused => "No password is used by more than one entry.",
+		};
+		frame.render_widget(Paragraph::new(text).centered().block(block), area);
+		return;
+	}
+
+	let table = match app.audit_view {
+		AuditView::Findings => findings_table(&app.items, &app.findings, &app.colors),
+		AuditView::Reused => reuse_table(&app.items, &app.reuse_groups, &app.colors),
+	}
+	.row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+	.block(block);
+
+	frame.render_stateful_widget(table, area, &mut app.audit_state);
+}
+
+fn findings_table<'a>(items: &'a [Password], findings: &'a [Finding], colors: &TableColors) -> Table<'a> {
+	let rows = findings.iter().map(|finding| {
+		let data = &items[finding.index];
+		let issues: Vec<String> = finding.issues.iter().map(Issue::describe).collect();
+		let score = finding
+			.issues
+			.iter()
+			.find_map(|issue| match issue {
+				Issue::Weak(strength) => Some(strength.score),
+				_ => None,
+			})
+			.unwrap_or(Score::VeryWeak);
+
+		Row::new([
+			Cell::from(finding.risk.to_string()).fg(strength_color(score)),
+			Cell::from(data.service()),
+			Cell::from(data.login()),
+			Cell::from(issues.join(", ")),
+		])
+	});
+
+	Table::new(
+		rows,
+		[
+			Constraint::Length(5),
+			Constraint::Fill(1),
+			Constraint::Fill(1),
+			Constraint::Fill(3),
+		],
+	)
+	.header(
+		Row::new(["Risk", "Service", "Login", "Issues"])
+			.fg(colors.header_fg)
+			.bold(),
+	)
+}
+
+// One row per entry, entries sharing a password get the same group number
+fn reuse_table<'a>(items: &'a [Password], groups: &'a [Vec<usize>], colors: &TableColors) -> Table<'a> {
+	let (even, odd) = (colors.row_fg, colors.header_fg);
+	let rows = groups.iter().enumerate().flat_map(move |(group, indices)| {
+		indices.iter().map(move |index| {
+			let data = &items[*index];
+			Row::new([
+				Cell::from(format!("#{}", group + 1)),
+				Cell::from(indices.len().to_string()),
+				Cell::from(data.service()),
+				Cell::from(data.login()),
+			])
+			.fg(match group % 2 {
+				0 => even,
+				_ => odd,
+			})
+		})
+	});
+
+	Table::new(
+		rows,
+		[
+			Constraint::Length(6),
+			Constraint::Length(7),
+			Constraint::Fill(1),
+			Constraint::Fill(1),
+		],
+	)
+	.header(
+		Row::new(["Group", "Shared", "Service", "Login"])
+			.fg(colors.header_fg)
+			.bold(),
+	)
+}
+
+// Deleted entries, newest first, with the day they go for good
+fn render_trash(app: &mut App, frame: &mut Frame) {
+	let area = centered_rect(80, 70, frame.area());
+	let block = Block::default()
+		.title(format!("Trash ({})", app.trash.len()))
+		.title(Line::from("(r, Enter) restore | (d) purge | (D) purge all | (Esc) back").right_aligned())
+		.borders(Borders::ALL)
+		.bg(Color::Reset);
+
+	frame.render_widget(Clear, area);
+	if app.trash.is_empty() {
+		frame.render_widget(Paragraph::new("The trash is empty.").centered().block(block), area);
+		return;
+	}
+
+	let retention = app.config.trash.retention_days;
+	let rows = app.trash_rows().into_iter().map(|index| {
+		let trashed = &app.trash[index];
+		let purge = trashed.purge_at(retention).map_or("never".into(), date::format);
+		Row::new([
+			Cell::from(trashed.item.service()),
+			Cell::from(trashed.item.login()),
+			Cell::from(date::format(trashed.deleted)),
+			Cell::from(purge),
+		])
+	});
+	let table = Table::new(
+		rows,
+		[
+			Constraint::Fill(1),
+			Constraint::Fill(1),
+			Constraint::Length(12),
+			Constraint::Length(12),
+		],
+	)
+	.header(
+		Row::new(["Service", "Login", "Deleted", "Purged"])
+			.fg(app.colors.header_fg)
+			.bold(),
+	)
+	.row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+	.block(block);
+
+	frame.render_stateful_widget(table, area, &mut app.trash_state);
+}
+
+fn render_confirm(app: &App, frame: &mut Frame) {
+	let question = match app.confirm {
+		Some(Confirm::Delete) => app
+			.selected_index()
+			.map(|index| format!("Move {} to the trash?", app.items[index].name())),
+		Some(Confirm::Purge) => app
+			.trash_state
+			.selected()
+			.and_then(|row| app.trash_rows().get(row).copied())
+			.map(|index| {
+				format!(
+					"Remove {} for good? It can't be restored.",
+					app.trash[index].item.name()
+				)
+			}),
+		Some(Confirm::EmptyTrash) => Some(format!(
+			"Remove all {} entries in the trash for good? They can't be restored.",
+			app.trash.len()
+		)),
+		None => None,
+	};
+
+	let area = centered_rect(50, 20, frame.area());
+	let area = Rect {
+		y: area.y + area.height.saturating_sub(5) / 2,
+		height: area.height.min(5),
+		..area
+	};
+	let block = Block::default()
+		.title("Confirm")
+		.title(Line::from("(y) yes | (n) no").right_aligned())
+		.borders(Borders::ALL)
+		.bg(Color::Reset);
+	let text = Paragraph::new(question.unwrap_or_default())
+		.wrap(Wrap { trim: true })
+		.centered()
+		.block(block);
+
+	frame.render_widget(Clear, area);
+	frame.render_widget(text, area);
+}
+
+// Sized to the code, dark on light so phones can scan it whatever the terminal theme is
+fn render_qr(app: &App, frame: &mut Frame) {
+	let width = app.qr.first().map_or(0, |line| line.chars().count()) as u16 + 2;
+	let height = app.qr.len() as u16 + 2;
+	let screen = frame.area();
+	let block = Block::default()
+		.title("TOTP")
+		.title(Line::from("(Esc) back").right_aligned())
+		.borders(Borders::ALL)
+		.bg(Color::Reset);
+
+	if width > screen.width || height > screen.height {
+		let area = centered_rect(60, 20, screen);
+		frame.render_widget(Clear, area);
+		frame.render_widget(
+			Paragraph::new("Terminal is too small for the QR code, try `psu qr`.")
+				.centered()
+				.block(block),
+			area,
+		);
+		return;
+	}
+
+	let area = Rect::new(
+		screen.x + (screen.width - width) / 2,
+		screen.y + (screen.height - height) / 2,
+		width,
+		height,
+	);
+	let code = Paragraph::new(Text::from_iter(app.qr.iter().map(String::as_str)))
+		.style(Style::new().fg(Color::Black).bg(Color::White));
+
+	// The block first, its background would cover the white of the code otherwise
+	let inner = block.inner(area);
+	frame.render_widget(Clear, area);
+	frame.render_widget(block, area);
+	frame.render_widget(code, inner);
+}
+
 fn render_scrollbar(app: &mut App, frame: &mut Frame, area: Rect) {
 	frame.render_stateful_widget(
 		Scrollbar::default()
@@ -192,12 +708,30 @@ fn render_scrollbar(app: &mut App, frame: &mut Frame, area: Rect) {
 }
 
 pub fn render_popup(app: &App, frame: &mut Frame) {
-	let popup_block = Block::default()
+	let fields = app.form_fields();
+	let mut popup_block = Block::default()
 		.title("Form")
 		.borders(Borders::ALL)
 		.style(Style::default())
 		.bg(Color::Reset);
-	let area = centered_rect(60, 37, frame.area());
+	if let Some(err) = &app.form_error {
+		popup_block = popup_block.title(Line::from(err.as_str()).red());
+	}
+
+	// Three lines per field and the border
+	let width = centered_rect(60, 100, frame.area());
+	let height = (fields.len() as u16 * 3 + 2).min(width.height);
+	let area = Rect {
+		y: width.y + (width.height - height) / 2,
+		height,
+		..width
+	};
+
+	// Forms taller than the terminal scroll so the current field stays in view
+	let visible = (height.saturating_sub(2) / 3).max(1) as usize;
+	let current = fields.iter().position(|mode| *mode == app.input_mode).unwrap_or(0);
+	let first = (current + 1).saturating_sub(visible);
+	let shown = &fields[first..(first + visible).min(fields.len())];
 
 	frame.render_widget(Clear, area);
 	frame.render_widget(popup_block, area);
@@ -205,59 +739,225 @@ pub fn render_popup(app: &App, frame: &mut Frame) {
 	let popup_chunks = Layout::default()
 		.direction(Direction::Vertical)
 		.margin(1)
-		.constraints([Constraint::Fill(1), Constraint::Fill(1), Constraint::Fill(1)])
+		.constraints(shown.iter().map(|_| Constraint::Length(3)))
 		.split(area);
 
-	let mut service_block = Block::default().title("Service").borders(Borders::ALL);
-	let mut login_block = Block::default().title("Login or Email").borders(Borders::ALL);
-	let mut password_block = Block::default().title("Password").borders(Borders::ALL);
-
 	let active_style = Style::default().fg(Color::Blue);
+	let matches = app.input.password() == app.input.confirm.value();
+	let blank = Input::default();
+
+	for (mode, chunk) in shown.iter().zip(popup_chunks.iter()) {
+		let field = match mode {
+			InputMode::Field(index) => app.input.fields.get(*index),
+			_ => None,
+		};
+		let (title, hint, input) = match mode {
+			InputMode::Service => ("Service", "", &app.input.service),
+			InputMode::Login => ("Login or Email", "", &app.input.login),
+			InputMode::Password => (
+				"Password",
+				"(Ctrl-g) generate | (Ctrl-p) passphrase | (Ctrl-r) show",
+				&app.input.password,
+			),
+			InputMode::Confirm => (
+				"Confirm password",
+				if matches { "" } else { "doesn't match" },
+				&app.input.confirm,
+			),
+			InputMode::Totp => ("TOTP secret", "optional, base32 or otpauth://", &app.input.totp),
+			InputMode::Url => ("URL", "optional", &app.input.url),
+			InputMode::Folder => ("Folder", "optional, / nests folders", &app.input.folder),
+			InputMode::Expires => ("Expires", "optional, YYYY-MM-DD", &app.input.expires),
+			InputMode::Tags => ("Tags", "comma separated", &app.input.tags),
+			InputMode::Notes => ("Notes", "(Ctrl-n) new line", &app.input.notes),
+			InputMode::Field(_) => (
+				if field.is_some() { "Field" } else { "New field" },
+				match field.is_some_and(|field| field.secret) {
+					true => "name: value | secret, (Ctrl-s) make plain",
+					false => "name: value | (Ctrl-s) make secret",
+				},
+				field.map_or(&blank, |field| &field.input),
+			),
+		};
+		let input = match mode {
+			InputMode::Password | InputMode::Confirm if !app.show_password => mask_input(input),
+			InputMode::Field(_) if field.is_some_and(|field| field.secret) && !app.show_password => mask_field(input),
+			InputMode::Notes => Input::new(input.value().replace('\n', NEWLINE_SYMBOL)).with_cursor(input.cursor()),
+			_ => input.clone(),
+		};
 
-	match app.input_mode {
-		InputMode::Service => service_block = service_block.style(active_style),
-		InputMode::Login => login_block = login_block.style(active_style),
-		InputMode::Password => password_block = password_block.style(active_style),
+		let mut block = Block::default()
+			.title(title)
+			.title(Line::from(hint).right_aligned())
+			.borders(Borders::ALL);
+		if *mode == app.input_mode {
+			block = block.style(active_style);
+		}
+
+		// Keep the cursor in view on long values, the text scrolls with it
+		let width = chunk.width.max(3) - 3;
+		let scroll = input.visual_scroll(width as usize);
+		let text = Paragraph::new(input.value())
+			.scroll((0, scroll as u16))
+			.fg(Color::White)
+			.block(block);
+		frame.render_widget(text, *chunk);
+
+		if *mode == app.input_mode {
+			let x = input.visual_cursor().max(scroll) - scroll + 1;
+			frame.set_cursor_position((chunk.x + x as u16, chunk.y + 1));
+		}
 	}
+}
 
-	let service_text = Paragraph::new(app.input.service())
-		.fg(Color::White)
-		.block(service_block);
-	frame.render_widget(service_text, popup_chunks[0]);
+fn render_unlock(app: &App, frame: &mut Frame) {
+	let title = if app.vault_path().exists() {
+		"Unlock vault"
+	} else if app.unlock.pending.is_some() {
+		"Repeat master password"
+	} else {
+		"Create master password"
+	};
+	let area = centered_rect(50, 30, frame.area());
+	let chunks = Layout::default()
+		.direction(Direction::Vertical)
+		.constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Min(1)])
+		.split(area);
 
-	let login_text = Paragraph::new(app.input.login()).fg(Color::White).block(login_block);
-	frame.render_widget(login_text, popup_chunks[1]);
+	if let Some(count) = app.unlock.migrate {
+		let info = format!(
+			"Found {count} entries in plaintext {LEGACY_FILE}. They will be encrypted into {VAULT_FILE}, \
+			 verified, and {LEGACY_FILE} will be securely removed."
+		);
+		frame.render_widget(
+			Paragraph::new(info).fg(Color::Yellow).wrap(Wrap { trim: true }),
+			chunks[0],
+		);
+	}
 
-	let password = Paragraph::new(app.input.password())
-		.fg(Color::White)
-		.block(password_block);
-	frame.render_widget(password, popup_chunks[2]);
+	let input = mask_input(&app.unlock.input);
+	let block = Block::default()
+		.title(title)
+		.borders(Borders::ALL)
+		.style(Style::default().fg(Color::Blue));
+	frame.render_widget(Paragraph::new(input.value()).fg(Color::White).block(block), chunks[1]);
 
-	let (chunk, text) = match app.input_mode {
-		InputMode::Service => (popup_chunks[0], &app.input.service),
-		InputMode::Login => (popup_chunks[1], &app.input.login),
-		InputMode::Password => (popup_chunks[2], &app.input.password),
+	let hint = match &app.unlock.error {
+		Some(err) => Paragraph::new(err.as_str()).fg(Color::Red),
+		None => Paragraph::new("(Enter) unlock | (Esc) quit").fg(app.colors.row_fg),
 	};
+	frame.render_widget(hint.centered(), chunks[2]);
 
-	let width = chunk.width.max(3) - 3;
-	let scroll = text.visual_scroll(width as usize);
-	let x = text.visual_cursor().max(scroll) - scroll + 1;
-	frame.set_cursor_position((chunk.x + x as u16, chunk.y + 1));
+	let width = chunks[1].width.max(3) - 3;
+	let scroll = input.visual_scroll(width as usize);
+	let x = input.visual_cursor().max(scroll) - scroll + 1;
+	frame.set_cursor_position((chunks[1].x + x as u16, chunks[1].y + 1));
+}
+
+fn render_migrate(app: &App, frame: &mut Frame) {
+	let area = centered_rect(50, 30, frame.area());
+	let mut text = Text::from(vec![
+		format!("A plaintext {LEGACY_FILE} was found next to {VAULT_FILE}.").into(),
+		"Keeping both would leave your passwords unencrypted on disk.".into(),
+		"".into(),
+		format!("(Enter) import missing entries and securely remove {LEGACY_FILE} | (Esc) quit").into(),
+	]);
+	if let Some(err) = &app.unlock.error {
+		text.push_line("");
+		text.push_line(Line::from(err.as_str()).red());
+	}
+
+	let block = Block::default()
+		.title("Legacy file")
+		.borders(Borders::ALL)
+		.style(Style::default().fg(Color::Yellow));
+	frame.render_widget(
+		Paragraph::new(text).block(block).wrap(Wrap { trim: true }).centered(),
+		area,
+	);
+}
+
+// Replace every character with MASK_SYMBOL, keeping the cursor on the same character
+fn mask_input(input: &Input) -> Input {
+	Input::new(MASK_SYMBOL.repeat(input.value().chars().count())).with_cursor(input.cursor())
+}
+
+// Same for the value of a "name: value" custom field, the name stays readable
+fn mask_field(input: &Input) -> Input {
+	let value = input.value();
+	let name = value.find(':').map_or(0, |colon| colon + 1);
+	let name = value.len() - value[name..].trim_start().len();
+	let masked = MASK_SYMBOL.repeat(value[name..].chars().count());
+	Input::new(format!("{}{masked}", &value[..name])).with_cursor(input.cursor())
 }
 
 fn render_footer(app: &App, frame: &mut Frame, area: Rect) {
-	let info_footer = Paragraph::new(Text::from_iter(INFO_TEXT))
-		.style(Style::new().fg(app.colors.row_fg).bg(app.colors.buffer_bg))
-		.centered()
-		.block(
-			Block::bordered()
-				.border_type(BorderType::Double)
-				.border_style(Style::new().fg(app.colors.footer_border_color)),
-		);
+	let block = Block::bordered()
+		.border_type(BorderType::Double)
+		.border_style(Style::new().fg(app.colors.footer_border_color));
+	let style = Style::new().fg(app.colors.row_fg).bg(app.colors.buffer_bg);
+	if app.current_screen == CurrentScreen::Move {
+		return render_move(app, frame, area, block.style(style));
+	}
+	let text = match (&app.status, app.clip_left()) {
+		_ if app.current_screen == CurrentScreen::Search => None,
+		(Some(status), _) => Some(Text::from(status.as_str())),
+		(None, Some(left)) => Some(Text::from(format!("Copied, the clipboard is cleared in {left}s"))),
+		(None, None) if !app.filter.value().is_empty() => None,
+		(None, None) => Some(Text::from_iter(INFO_TEXT)),
+	};
+	let Some(text) = text else {
+		return render_filter(app, frame, area, block.style(style));
+	};
+	let info_footer = Paragraph::new(text).style(style).centered().block(block);
 	frame.render_widget(info_footer, area);
 }
 
-fn constraint_len_calculator<T: Data>(items: &[T]) -> (u16, u16, u16) {
+// Search line, with the cursor while it's being typed
+fn render_filter(app: &App, frame: &mut Frame, area: Rect, block: Block) {
+	let hint = match app.current_screen {
+		CurrentScreen::Search => "(Enter) keep filter | (Esc) clear",
+		_ => "(/) edit | (Esc) clear",
+	};
+	let block = block
+		.title(format!(" {} of {} ", app.view.len(), app.items.len()))
+		.title(Line::from(format!(" {hint} ")).right_aligned());
+	let inner = block.inner(area);
+
+	// One column goes to the slash in front of the query
+	let width = inner.width.max(2) - 2;
+	let scroll = app.filter.visual_scroll(width as usize);
+	let text = Paragraph::new(format!("/{}", app.filter.value()))
+		.scroll((0, scroll as u16))
+		.block(block);
+	frame.render_widget(text, area);
+
+	if app.current_screen == CurrentScreen::Search {
+		let x = app.filter.visual_cursor().max(scroll) - scroll + 1;
+		frame.set_cursor_position((inner.x + x as u16, inner.y));
+	}
+}
+
+// Folder prompt of the Move screen
+fn render_move(app: &App, frame: &mut Frame, area: Rect, block: Block) {
+	let block = block
+		.title(" Move to folder ")
+		.title(Line::from(" (Enter) move | (Esc) cancel | empty for none ").right_aligned());
+	let inner = block.inner(area);
+
+	let width = inner.width as usize;
+	let scroll = app.folder_input.visual_scroll(width);
+	let text = Paragraph::new(app.folder_input.value())
+		.scroll((0, scroll as u16))
+		.block(block);
+	frame.render_widget(text, area);
+
+	let x = app.folder_input.visual_cursor().max(scroll) - scroll;
+	frame.set_cursor_position((inner.x + x as u16, inner.y));
+}
+
+// Only passwords on screen count for their column, masked ones would give their length away
+fn constraint_len_calculator<T: Data>(items: &[T], revealed: impl Fn(usize) -> bool) -> (u16, u16, u16) {
 	let service_len = items
 		.iter()
 		.map(Data::service)
@@ -274,10 +974,10 @@ fn constraint_len_calculator<T: Data>(items: &[T]) -> (u16, u16, u16) {
 
 	let password_len: u16 = items
 		.iter()
-		.map(Data::password)
-		.map(UnicodeWidthStr::width)
-		.max()
-		.unwrap_or(0) as u16;
+		.enumerate()
+		.filter(|(index, _)| revealed(*index))
+		.map(|(_, item)| item.password().width())
+		.fold(MASK_WIDTH, usize::max) as u16;
 
 	(service_len, login_len, password_len)
 }
@@ -0,0 +1,398 @@
+// #![warn(clippy::all, clippy::pedantic)]
+use crate::app::Password;
+use crate::history::History;
+use crate::trash::Trashed;
+use anyhow::{Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+	KeyInit, XChaCha20Poly1305, XNonce,
+	aead::{Aead, Payload as AeadPayload},
+};
+use rand::{RngCore, rngs::OsRng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+	fmt::Display,
+	fs::{self, OpenOptions},
+	io::Write,
+	path::{Path, PathBuf},
+};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+pub const VAULT_FILE: &str = "psu.vault";
+pub const VAULT_TEMP_FILE: &str = "psu.vault.temp";
+
+// Bump together with a new entry in UPGRADES whenever the payload layout changes
+pub const VAULT_VERSION: u16 = UPGRADES.len() as u16 + 1;
+
+const MAGIC: &[u8; 4] = b"PSU\0";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+// The header isn't authenticated before the key is derived, so a tampered file must not make
+// Argon2 allocate or run without bound
+const MAX_M_COST: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 64;
+const MAX_P_COST: u32 = 16;
+// Magic, format version, three KDF parameters, salt and nonce
+const HEADER_LEN: usize = MAGIC.len() + 2 + 3 * 4 + SALT_LEN + NONCE_LEN;
+
+// Payload upgrades, UPGRADES[n] turns a version n + 1 payload into a version n + 2 one
+const UPGRADES: &[fn(Value) -> Result<Value>] = &[assign_ids];
+
+#[derive(Debug, PartialEq)]
+pub enum VaultError {
+	// File is not a psu vault or the header is truncated
+	BadFormat,
+	// Authentication failed: wrong master password or tampered file
+	Decrypt,
+	// Written by a newer psu
+	UnsupportedVersion(u16),
+	// KDF parameters above the limits, most likely a tampered file
+	KdfLimits,
+}
+
+impl Display for VaultError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			VaultError::BadFormat => write!(f, "File is not a psu vault."),
+			VaultError::Decrypt => write!(f, "Wrong master password or corrupted vault."),
+			VaultError::UnsupportedVersion(version) => {
+				write!(
+					f,
+					"Vault version {version} is newer than this psu supports ({VAULT_VERSION})."
+				)
+			}
+			VaultError::KdfLimits => write!(f, "Vault asks for more key derivation work than psu allows."),
+		}
+	}
+}
+
+impl std::error::Error for VaultError {}
+
+// Everything stored inside the encrypted part of the vault
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Payload {
+	pub items: Vec<Password>,
+	// Deleted entries, newest last
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub trash: Vec<Trashed>,
+	// Undo and redo, only saved when the config asks for it
+	#[serde(default, skip_serializing_if = "History::is_empty")]
+	pub history: History,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KdfParams {
+	// Memory size in KiB
+	pub m_cost: u32,
+	pub t_cost: u32,
+	pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+	fn default() -> Self {
+		Self {
+			m_cost: Params::DEFAULT_M_COST,
+			t_cost: Params::DEFAULT_T_COST,
+			p_cost: Params::DEFAULT_P_COST,
+		}
+	}
+}
+
+// Unlocked vault: keeps the derived key in memory so it can be saved again
+pub struct Vault {
+	path: PathBuf,
+	params: KdfParams,
+	salt: [u8; SALT_LEN],
+	key: Zeroizing<[u8; KEY_LEN]>,
+}
+
+impl Vault {
+	pub fn create<T: AsRef<Path>>(path: T, master: &str, params: KdfParams) -> Result<Self> {
+		let mut salt = [0u8; SALT_LEN];
+		OsRng.fill_bytes(&mut salt);
+		let key = derive_key(master, &salt, params)?;
+
+		Ok(Self {
+			path: path.as_ref().into(),
+			params,
+			salt,
+			key,
+		})
+	}
+
+	pub fn open<T: AsRef<Path>>(path: T, master: &str) -> Result<(Self, Payload)> {
+		let path = path.as_ref();
+		let bytes = fs::read(path).with_context(|| format!("Failed to read {}.", path.display()))?;
+		let header = Header::parse(&bytes)?;
+		let key = derive_key(master, &header.salt, header.params)?;
+
+		let plain = Zeroizing::new(
+			XChaCha20Poly1305::new(key.as_ref().into())
+				.decrypt(
+					XNonce::from_slice(&header.nonce),
+					AeadPayload {
+						msg: &bytes[HEADER_LEN..],
+						aad: &bytes[..HEADER_LEN],
+					},
+				)
+				.map_err(|_| VaultError::Decrypt)?,
+		);
+		let payload = upgrade(header.version, serde_json::from_slice(&plain))?;
+
+		let vault = Self {
+			path: path.into(),
+			params: header.params,
+			salt: header.salt,
+			key,
+		};
+		// Upgrades can make up values like ids, they have to be the same on the next open
+		if header.version < VAULT_VERSION {
+			vault.save(&payload)?;
+		}
+		Ok((vault, payload))
+	}
+
+	// Encrypt the payload with a fresh nonce and atomically replace the vault file
+	pub fn save(&self, payload: &Payload) -> Result<()> {
+		let mut nonce = [0u8; NONCE_LEN];
+		OsRng.fill_bytes(&mut nonce);
+
+		let header = Header {
+			version: VAULT_VERSION,
+			params: self.params,
+			salt: self.salt,
+			nonce,
+		}
+		.to_bytes();
+		let plain = Zeroizing::new(serde_json::to_vec(payload).context("Failed to serialize vault.")?);
+		let cipher = XChaCha20Poly1305::new(self.key.as_ref().into())
+			.encrypt(
+				XNonce::from_slice(&nonce),
+				AeadPayload {
+					msg: &plain,
+					aad: &header,
+				},
+			)
+			.map_err(|_| anyhow::anyhow!("Failed to encrypt vault."))?;
+
+		let temp_path = self.path.with_file_name(VAULT_TEMP_FILE);
+		let mut file = private_file(&temp_path)?;
+		file.write_all(&header)?;
+		file.write_all(&cipher)?;
+		file.sync_all()?;
+
+		fs::rename(temp_path, &self.path)?;
+		Ok(())
+	}
+}
+
+struct Header {
+	version: u16,
+	params: KdfParams,
+	salt: [u8; SALT_LEN],
+	nonce: [u8; NONCE_LEN],
+}
+
+impl Header {
+	fn parse(bytes: &[u8]) -> Result<Self, VaultError> {
+		if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+			return Err(VaultError::BadFormat);
+		}
+
+		let mut rest = &bytes[MAGIC.len()..HEADER_LEN];
+		let mut take = |n: usize| {
+			let (head, tail) = rest.split_at(n);
+			rest = tail;
+			head
+		};
+		let version = u16::from_le_bytes(take(2).try_into().unwrap());
+		if version == 0 || version > VAULT_VERSION {
+			return Err(VaultError::UnsupportedVersion(version));
+		}
+
+		let mut u32_le = || u32::from_le_bytes(take(4).try_into().unwrap());
+		let params = KdfParams {
+			m_cost: u32_le(),
+			t_cost: u32_le(),
+			p_cost: u32_le(),
+		};
+		if params.m_cost > MAX_M_COST || params.t_cost > MAX_T_COST || params.p_cost > MAX_P_COST {
+			return Err(VaultError::KdfLimits);
+		}
+
+		Ok(Self {
+			version,
+			params,
+			salt: take(SALT_LEN).try_into().unwrap(),
+			nonce: take(NONCE_LEN).try_into().unwrap(),
+		})
+	}
+
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(HEADER_LEN);
+		bytes.extend_from_slice(MAGIC);
+		bytes.extend_from_slice(&self.version.to_le_bytes());
+		bytes.extend_from_slice(&self.params.m_cost.to_le_bytes());
+		bytes.extend_from_slice(&self.params.t_cost.to_le_bytes());
+		bytes.extend_from_slice(&self.params.p_cost.to_le_bytes());
+		bytes.extend_from_slice(&self.salt);
+		bytes.extend_from_slice(&self.nonce);
+		bytes
+	}
+}
+
+// Run every upgrade step between the stored version and VAULT_VERSION
+fn upgrade(version: u16, value: serde_json::Result<Value>) -> Result<Payload> {
+	let mut value = value.context("Failed to parse vault contents.")?;
+	for step in &UPGRADES[version as usize - 1..] {
+		value = step(value)?;
+	}
+
+	serde_json::from_value(value).context("Failed to parse vault contents.")
+}
+
+// Version 1 numbered entries by their position, version 2 gives each a random id that
+// stays the same when other entries are removed
+fn assign_ids(mut value: Value) -> Result<Value> {
+	let items = value
+		.get_mut("items")
+		.and_then(Value::as_array_mut)
+		.context("Vault has no list of entries.")?;
+	for item in items {
+		item.as_object_mut()
+			.context("Vault entry is not an object.")?
+			.insert("Id".into(), Uuid::new_v4().to_string().into());
+	}
+
+	Ok(value)
+}
+
+fn derive_key(master: &str, salt: &[u8], params: KdfParams) -> Result<Zeroizing<[u8; KEY_LEN]>> {
+	let params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(KEY_LEN))
+		.map_err(|err| anyhow::anyhow!("Invalid KDF parameters: {err}"))?;
+	let mut key = Zeroizing::new([0u8; KEY_LEN]);
+
+	Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+		.hash_password_into(master.as_bytes(), salt, key.as_mut())
+		.map_err(|err| anyhow::anyhow!("Failed to derive key: {err}"))?;
+
+	Ok(key)
+}
+
+// Open a file readable only by the owner
+fn private_file<T: AsRef<Path>>(path: T) -> Result<fs::File> {
+	let mut options = OpenOptions::new();
+	options.create(true).write(true).truncate(true);
+
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::OpenOptionsExt;
+		options.mode(0o600);
+	}
+
+	Ok(options.open(path)?)
+}
+
+#[cfg(test)]
+pub(crate) mod test_vault {
+	use super::{KdfParams, Payload, Vault, VaultError, upgrade};
+	use crate::app::Password;
+	use crate::trash::Trashed;
+	use std::path::PathBuf;
+
+	// Cheap parameters so the tests don't spend seconds in the KDF
+	pub const TEST_PARAMS: KdfParams = KdfParams {
+		m_cost: 64,
+		t_cost: 1,
+		p_cost: 1,
+	};
+
+	pub fn temp_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("psu-test-{name}-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	#[test]
+	fn round_trip() {
+		let path = temp_dir("round_trip").join(super::VAULT_FILE);
+		let vault = Vault::create(&path, "master", TEST_PARAMS).unwrap();
+		let payload = Payload {
+			items: vec![Password::new("a", "b", "c")],
+			trash: vec![Trashed {
+				item: Password::new("d", "e", "f"),
+				deleted: 1,
+			}],
+			..Default::default()
+		};
+		vault.save(&payload).unwrap();
+
+		let (_, saved) = Vault::open(&path, "master").unwrap();
+		assert_eq!(saved.items, payload.items);
+		assert_eq!(saved.trash, payload.trash);
+	}
+
+	#[test]
+	fn upgrade_ids() {
+		let v1 = serde_json::json!({ "items": [
+			{ "Id": 0, "Service": "a", "Login": "b", "Password": "c" },
+			{ "Id": 1, "Service": "d", "Login": "e", "Password": "f" },
+		]});
+
+		let payload = upgrade(1, Ok(v1)).unwrap();
+		assert_eq!(payload.items[1].ref_array()[1..], ["d", "e", "f"]);
+		assert_ne!(payload.items[0].id, payload.items[1].id);
+		assert!(payload.items.iter().all(|item| !item.id.is_nil()));
+	}
+
+	#[test]
+	fn wrong_password() {
+		let path = temp_dir("wrong_password").join(super::VAULT_FILE);
+		Vault::create(&path, "master", TEST_PARAMS)
+			.unwrap()
+			.save(&Payload::default())
+			.unwrap();
+
+		let err = Vault::open(&path, "not master").err().unwrap();
+		assert_eq!(err.downcast_ref::<VaultError>(), Some(&VaultError::Decrypt));
+	}
+
+	#[test]
+	fn newer_version() {
+		let path = temp_dir("newer_version").join(super::VAULT_FILE);
+		Vault::create(&path, "master", TEST_PARAMS)
+			.unwrap()
+			.save(&Payload::default())
+			.unwrap();
+
+		let mut bytes = std::fs::read(&path).unwrap();
+		bytes[4..6].copy_from_slice(&(super::VAULT_VERSION + 1).to_le_bytes());
+		std::fs::write(&path, bytes).unwrap();
+
+		let err = Vault::open(&path, "master").err().unwrap();
+		assert_eq!(
+			err.downcast_ref::<VaultError>(),
+			Some(&VaultError::UnsupportedVersion(super::VAULT_VERSION + 1))
+		);
+	}
+
+	#[test]
+	fn kdf_limits() {
+		let path = temp_dir("kdf_limits").join(super::VAULT_FILE);
+		Vault::create(&path, "master", TEST_PARAMS)
+			.unwrap()
+			.save(&Payload::default())
+			.unwrap();
+
+		// m_cost right after the magic and the version
+		let mut bytes = std::fs::read(&path).unwrap();
+		bytes[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+		std::fs::write(&path, bytes).unwrap();
+
+		let err = Vault::open(&path, "master").err().unwrap();
+		assert_eq!(err.downcast_ref::<VaultError>(), Some(&VaultError::KdfLimits));
+	}
+}